@@ -0,0 +1,107 @@
+//! The remaining time spoken through a user command, e.g. speech-dispatcher's `spd-say`
+use std::time::Duration;
+
+use smol::process::Command;
+
+use crate::config::ShellCommand;
+
+/// Announces a countdown when it starts and whenever it crosses a multiple of the interval
+#[derive(Debug, Clone, Copy)]
+pub struct Announcer {
+    interval: Duration,
+    /// The countdown announced last and how many intervals it had left then
+    last: Option<(&'static str, u64)>
+}
+
+impl Announcer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None
+        }
+    }
+
+    /// `None` if there's nothing new to announce
+    pub fn announcement(&mut self, countdown: &'static str, remaining: Duration) -> Option<String> {
+        if remaining.is_zero() {
+            return None;
+        }
+
+        let intervals = remaining.as_secs().div_ceil(self.interval.as_secs().max(1));
+        if let Some((last, last_intervals)) = self.last
+            && last == countdown
+            && intervals >= last_intervals
+        {
+            return None;
+        }
+
+        self.last = Some((countdown, intervals));
+        let mins = remaining.as_secs().div_ceil(60);
+        let unit = if mins == 1 { "minute" } else { "minutes" };
+        Some(format!("{countdown}, {mins} {unit} left"))
+    }
+}
+
+/// Runs `command` with the announcement appended in the background, failures are only reported on
+/// stderr
+pub fn announce(command: ShellCommand, announcement: String) {
+    smol::spawn(async move {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{command} \"$1\""))
+            .arg("annoyodoro")
+            .arg(&announcement)
+            .status()
+            .await;
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("Announcing with `{command}` exited with {status}"),
+            Err(err) => eprintln!("Cannot announce with `{command}`: {err}")
+        }
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn announces_the_start_and_each_interval() {
+        let mut announcer = Announcer::new(5 * MINUTE);
+        assert_eq!(
+            announcer.announcement("Work", 20 * MINUTE).as_deref(),
+            Some("Work, 20 minutes left")
+        );
+        assert_eq!(announcer.announcement("Work", 19 * MINUTE), None);
+        assert_eq!(announcer.announcement("Work", 16 * MINUTE), None);
+        assert_eq!(
+            announcer.announcement("Work", 15 * MINUTE).as_deref(),
+            Some("Work, 15 minutes left")
+        );
+        assert_eq!(announcer.announcement("Work", 14 * MINUTE), None);
+    }
+
+    #[test]
+    fn announces_a_new_countdown_right_away() {
+        let mut announcer = Announcer::new(5 * MINUTE);
+        announcer.announcement("Work", 20 * MINUTE);
+        assert_eq!(
+            announcer.announcement("Break", MINUTE).as_deref(),
+            Some("Break, 1 minute left")
+        );
+        assert_eq!(
+            announcer.announcement("Work", 20 * MINUTE).as_deref(),
+            Some("Work, 20 minutes left")
+        );
+    }
+
+    #[test]
+    fn snoozing_is_not_announced() {
+        let mut announcer = Announcer::new(5 * MINUTE);
+        announcer.announcement("Work", 3 * MINUTE);
+        assert_eq!(announcer.announcement("Work", 8 * MINUTE), None);
+    }
+}
@@ -1,174 +1,372 @@
 use std::{
     mem,
-    sync::mpsc::{SyncSender, sync_channel},
-    time::Instant
+    time::{Duration, Instant}
 };
 
 use iced::{
-    Element, Length, Task, Theme,
+    Element, Event, Length, Theme,
     alignment::{Horizontal, Vertical},
-    exit, never,
-    widget::{self, button, column, container, operation::focus, rich_text, span, stack},
-    window
+    mouse, never,
+    widget::{self, button, column, container, rich_text, row, span, stack}
 };
-use iced_layershell::{application, to_layer_message};
 use jiff::SignedDuration;
-use mpris::{PlaybackStatus, PlayerFinder};
 use yanet::Result;
 
-use crate::{circular::Circular, config::Config, view::TIMER_TEXT_SIZE};
+use crate::{
+    breathing::{Breathing, BreathingPattern},
+    circular::Circular,
+    clock::Clock,
+    config::{Config, RingColors},
+    keybindings::{Action, Keybindings},
+    sounds::{Cue, Sounds},
+    suggestions::Rotation,
+    view::Metrics
+};
 
+/// The break countdown and the prompt for the goal of the next work session, shown either on the
+/// layer-shell overlay or in a fullscreen window
 #[derive(Clone)]
 pub struct BreakTimer {
-    work_goal_tx: SyncSender<String>,
     last_tick: Instant,
     long_break: bool,
     break_duration_left: SignedDuration,
     break_duration: SignedDuration,
     work_goal: String,
-    theme: Theme
+    /// `None` if the break counts down regardless of input
+    idle_after: Option<Duration>,
+    last_input: Option<Instant>,
+    suggestions: Rotation,
+    suggestion_done: bool,
+    breathing_pattern: BreathingPattern,
+    /// Shown instead of the countdown while it's `Some`
+    breathing: Option<Breathing>,
+    summary: BreakSummary,
+    theme: Theme,
+    ring_colors: RingColors,
+    keybindings: Keybindings,
+    /// Keeps the breathing ring still
+    reduced_motion: bool,
+    sounds: Sounds
 }
 
-impl BreakTimer {
-    pub fn spawn(long_break: bool, config: Config) -> Result<String> {
-        let mut was_playing_before_break = false;
-        let player = PlayerFinder::new()?.find_active().ok();
-        if let Some(player) = &player
-            && player.get_playback_status()? == PlaybackStatus::Playing
-        {
-            was_playing_before_break = true;
-            player.pause()?;
+/// Today's progress, shown beneath the ring
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BreakSummary {
+    pub pomodori_today: u32,
+    /// 0 for no target
+    pub daily_target: u32,
+    pub goals_today: usize,
+    pub long_break_in: u16,
+    /// Consecutive days with pomodori, including today
+    pub streak: u32
+}
+
+#[derive(Debug, Clone)]
+pub enum BreakMessage {
+    ContinueWorking,
+    WorkGoalChange(String),
+    NextSuggestion,
+    SuggestionDone,
+    ToggleBreathing,
+    /// Keyboard, mouse or touch input on the break surface
+    Input,
+    Tick
+}
+
+impl BreakMessage {
+    /// [`BreakMessage::Input`] for the events caused by touching the machine
+    pub fn from_input(event: Event) -> Option<Self> {
+        match event {
+            Event::Keyboard(_)
+            | Event::Touch(_)
+            | Event::Mouse(
+                mouse::Event::CursorMoved { .. }
+                | mouse::Event::ButtonPressed(_)
+                | mouse::Event::WheelScrolled { .. }
+            ) => Some(Self::Input),
+            _ => None
         }
+    }
+}
 
-        let duration = if long_break {
-            config.pomodoro.long_break_duration
-        } else {
-            config.pomodoro.break_duration
-        };
+impl BreakTimer {
+    pub fn new(
+        long_break: bool,
+        config: Config,
+        sounds: Sounds,
+        suggestions: Rotation,
+        summary: BreakSummary,
+        clock: &impl Clock
+    ) -> Result<Self> {
+        let duration = config.pomodoro.break_duration(long_break);
 
-        let (work_goal_tx, work_goal_rx) = sync_channel(1);
-        let timer = BreakTimer {
-            last_tick: Instant::now(),
+        Ok(BreakTimer {
+            last_tick: clock.now(),
             long_break,
             break_duration_left: duration.try_into()?,
             theme: config.theme(),
-            work_goal_tx,
+            ring_colors: config.ring_colors(),
+            keybindings: config.keybindings,
+            reduced_motion: config.accessibility.reduced_motion,
             work_goal: String::new(),
-            break_duration: duration.try_into()?
-        };
+            break_duration: duration.try_into()?,
+            idle_after: config.absence.idle_after(),
+            last_input: None,
+            suggestions,
+            suggestion_done: false,
+            breathing_pattern: config.breathing.pattern,
+            breathing: config
+                .breathing
+                .enabled
+                .then(|| Breathing::new(config.breathing.pattern, clock)),
+            summary,
+            sounds
+        })
+    }
 
-        application(
-            move || (timer.clone(), focus("work-goal")),
-            "annoyodoro",
-            BreakTimer::update,
-            BreakTimer::view
-        )
-        .default_font(config.font)
-        .theme(|app: &BreakTimer| app.theme.clone())
-        .subscription(|_| window::frames().map(Message::Tick))
-        .run()?;
-
-        let work_goal = work_goal_rx
-            .try_recv()
-            .expect("Work goal should have been sent");
-
-        if let Some(player) = player
-            && was_playing_before_break
-        {
-            player.play()?
-        }
+    /// Whether the break waits for the input to stop before it counts down
+    pub fn requires_absence(&self) -> bool {
+        self.idle_after.is_some()
+    }
 
-        Ok(work_goal)
+    /// Whether the break doesn't count down because of recent input
+    fn touched(&self) -> bool {
+        self.break_duration_left.is_positive()
+            && self
+                .idle_after
+                .zip(self.last_input)
+                .is_some_and(|(idle_after, input)| self.last_tick < input + idle_after)
     }
-}
 
-#[to_layer_message]
-#[derive(Debug, Clone)]
-enum Message {
-    ContinueWorking,
-    WorkGoalChange(String),
-    Tick(Instant)
-}
+    /// Zero once the break is over
+    pub fn remaining(&self) -> Duration {
+        self.break_duration_left.try_into().unwrap_or_default()
+    }
 
-impl BreakTimer {
-    fn update(&mut self, message: Message) -> Task<Message> {
+    pub fn set_theme(&mut self, theme: Theme, ring_colors: RingColors) {
+        self.theme = theme;
+        self.ring_colors = ring_colors;
+    }
+
+    /// Whether the breathing guide is shown, its ring is animated
+    pub fn breathing(&self) -> bool {
+        self.breathing.is_some()
+    }
+
+    /// Whether the user did any of the suggested activities during the break
+    pub fn suggestion_done(&self) -> bool {
+        self.suggestion_done
+    }
+
+    /// Returns the goal of the next work session once it's submitted
+    pub fn update(&mut self, message: BreakMessage, clock: &impl Clock) -> Option<String> {
         match message {
-            Message::ContinueWorking if !self.work_goal.is_empty() => {
-                self.work_goal_tx
-                    .send(mem::take(&mut self.work_goal))
-                    .unwrap();
-                return exit()
+            BreakMessage::ContinueWorking if !self.work_goal.is_empty() => {
+                return Some(mem::take(&mut self.work_goal));
             }
-            Message::ContinueWorking => {}
-            Message::Tick(now) => {
-                self.break_duration_left -= now.duration_since(self.last_tick).try_into().unwrap();
+            BreakMessage::ContinueWorking => {}
+            BreakMessage::Tick => {
+                let now = clock.now();
+                let was_positive = self.break_duration_left.is_positive();
+                // Absence is only required until the break is over
+                let counted_from = match self.idle_after.zip(self.last_input) {
+                    Some((idle_after, input)) if was_positive => {
+                        self.last_tick.max(input + idle_after)
+                    }
+                    _ => self.last_tick
+                };
+                self.break_duration_left -= now
+                    .saturating_duration_since(counted_from)
+                    .try_into()
+                    .unwrap();
                 self.last_tick = now;
+                if was_positive && !self.break_duration_left.is_positive() {
+                    self.sounds.play(Cue::BreakEnd);
+                }
+            }
+            BreakMessage::WorkGoalChange(goal) => self.work_goal = goal,
+            BreakMessage::NextSuggestion => self.suggestions.advance(),
+            BreakMessage::SuggestionDone => self.suggestion_done = true,
+            BreakMessage::Input if self.idle_after.is_some() => {
+                self.last_input = Some(clock.now());
+            }
+            BreakMessage::Input => {}
+            BreakMessage::ToggleBreathing => {
+                self.breathing = match self.breathing {
+                    Some(_) => None,
+                    None => Some(Breathing::new(self.breathing_pattern, clock))
+                }
             }
-            Message::WorkGoalChange(goal) => self.work_goal = goal,
-            Message::AnchorChange(_)
-            | Message::SetInputRegion(_)
-            | Message::AnchorSizeChange(..)
-            | Message::LayerChange(_)
-            | Message::MarginChange(_)
-            | Message::SizeChange(_)
-            | Message::ExclusiveZoneChange(_)
-            | Message::KeyboardInteractivityChange(_)
-            | Message::VirtualKeyboardPressed { .. } => {}
         }
 
-        Task::none()
+        None
     }
 
-    fn view(&self) -> Element<'_, Message> {
+    fn summary_view(&self, metrics: Metrics) -> Element<'_, BreakMessage> {
+        let BreakSummary {
+            pomodori_today,
+            daily_target,
+            goals_today,
+            long_break_in,
+            streak
+        } = self.summary;
+        let primary = self.theme.palette().primary;
+        let pomodori = if daily_target == 0 {
+            pomodori_today.to_string()
+        } else {
+            format!("{pomodori_today} / {daily_target}")
+        };
+        let pomodori_unit = if long_break_in == 1 {
+            "pomodoro"
+        } else {
+            "pomodori"
+        };
+        let days_unit = if streak == 1 { "day" } else { "days" };
+        let rows = [
+            ("Pomodori today", pomodori),
+            ("Goals worked on today", goals_today.to_string()),
+            (
+                "Next long break in",
+                format!("{long_break_in} {pomodori_unit}")
+            ),
+            ("Streak", format!("{streak} {days_unit}"))
+        ]
+        .into_iter()
+        .map(|(label, value)| {
+            row![
+                container(label).width(Length::Fill),
+                container(widget::text(value).color(primary)).align_right(Length::Fill)
+            ]
+            .into()
+        });
+
+        column(rows).spacing(metrics.spacing).into()
+    }
+
+    pub fn view(&self, metrics: Metrics) -> Element<'_, BreakMessage> {
         let palette = self.theme.palette();
         let (title_text, timer_color) = if self.break_duration_left <= SignedDuration::ZERO {
-            ("Time to work! (submit your work reason)", palette.danger)
+            (
+                "Time to work! (submit your work reason)",
+                self.ring_colors.overtime
+            )
+        } else if self.touched() {
+            (
+                "Step away, the break only counts down while you're away",
+                palette.danger
+            )
         } else if self.long_break {
-            ("Time for a long break", palette.primary)
+            ("Time for a long break", self.ring_colors.long_break)
         } else {
-            ("Time for a break!", palette.primary)
+            ("Time for a break!", self.ring_colors.short_break)
         };
 
         let time_left = self.break_duration_left;
         let on_submit =
-            (!self.break_duration_left.is_positive()).then_some(Message::ContinueWorking);
+            (!self.break_duration_left.is_positive()).then_some(BreakMessage::ContinueWorking);
 
         let text_input = widget::text_input("Work goal", &self.work_goal)
             .id("work-goal")
-            .on_input(Message::WorkGoalChange)
+            .on_input(BreakMessage::WorkGoalChange)
             .on_submit_maybe(on_submit);
-        let timer = stack![
-            Circular {
-                percentage: 1.0
-                    - self.break_duration_left.as_millis_f32()
-                        / self.break_duration.as_millis_f32(),
-                color: timer_color,
-                theme: self.theme.clone()
+        let time_left_text = rich_text![
+            span(time_left.as_mins().to_string()).color(timer_color),
+            span(":").color(self.theme.extended_palette().background.strong.color),
+            span(format!("{:02}", time_left.as_secs().abs() % 60)).color(timer_color)
+        ]
+        .on_link_click(never);
+        let timer = match self.breathing {
+            Some(breathing) => {
+                let (phase, size) = breathing.at(self.last_tick);
+                let size = if self.reduced_motion { 1.0 } else { size };
+                stack![
+                    Circular {
+                        percentage: 1.0,
+                        color: self.ring_colors.for_break(self.long_break),
+                        track: self.ring_colors.track,
+                        theme: self.theme.clone(),
+                        size,
+                        diameter: metrics.ring_size,
+                        stroke_width: metrics.ring_width
+                    },
+                    container(
+                        column![
+                            widget::text(phase)
+                                .size(metrics.text_size)
+                                .color(palette.primary),
+                            time_left_text
+                        ]
+                        .align_x(Horizontal::Center)
+                    )
+                    .center(Length::Fill)
+                ]
+            }
+            None => stack![
+                Circular {
+                    percentage: 1.0
+                        - self.break_duration_left.as_millis_f32()
+                            / self.break_duration.as_millis_f32(),
+                    color: timer_color,
+                    track: self.ring_colors.track,
+                    theme: self.theme.clone(),
+                    size: 1.0,
+                    diameter: metrics.ring_size,
+                    stroke_width: metrics.ring_width
+                },
+                container(time_left_text.size(metrics.timer_text_size)).center(Length::Fill)
+            ]
+        };
+        let toggle_breathing = button(widget::text(self.keybindings.label(
+            if self.breathing.is_some() {
+                "Show the countdown"
+            } else {
+                "Breathing exercise"
             },
-            container(
-                rich_text![
-                    span(time_left.as_mins().to_string()).color(timer_color),
-                    span(":").color(self.theme.extended_palette().background.strong.color),
-                    span(format!("{:02}", time_left.as_secs().abs() % 60)).color(timer_color)
+            Action::ToggleBreathing
+        )))
+        .style(button::secondary)
+        .on_press(BreakMessage::ToggleBreathing);
+        let suggestion = self.suggestions.current().map(|suggestion| {
+            let done = button(widget::text(if self.suggestion_done {
+                "Done".to_string()
+            } else {
+                self.keybindings.label("Did it", Action::SuggestionDone)
+            }))
+            .on_press_maybe((!self.suggestion_done).then_some(BreakMessage::SuggestionDone));
+            column![
+                widget::text(suggestion)
+                    .size(metrics.text_size)
+                    .color(palette.primary),
+                row![
+                    button(widget::text(
+                        self.keybindings
+                            .label("Another one", Action::NextSuggestion)
+                    ))
+                    .style(button::secondary)
+                    .on_press(BreakMessage::NextSuggestion),
+                    done
                 ]
-                .on_link_click(never)
-                .size(TIMER_TEXT_SIZE)
-            )
-            .center(Length::Fill)
-        ];
+                .spacing(metrics.spacing)
+            ]
+            .align_x(Horizontal::Center)
+            .spacing(metrics.spacing)
+        });
         let column = column![
-            widget::text(title_text).size(30),
+            widget::text(title_text).size(30.0 * metrics.scale),
             timer,
+            self.summary_view(metrics),
+            toggle_breathing,
+            suggestion,
             "Enter the goal of your next work session",
             text_input,
         ]
         .align_x(Horizontal::Center)
-        .max_width(TIMER_TEXT_SIZE * 3.0);
+        .max_width(metrics.timer_text_size * 3.0);
 
         #[cfg(debug_assertions)]
         let column = column.push(
             button("Skip break button (enabled only when debugging)")
-                .on_press(Message::ContinueWorking)
+                .on_press(BreakMessage::ContinueWorking)
         );
 
         let container = container(column)
@@ -180,3 +378,130 @@ impl BreakTimer {
         container.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use iced::Color;
+
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn break_timer(clock: &FakeClock, duration: Duration) -> BreakTimer {
+        BreakTimer {
+            last_tick: clock.now(),
+            long_break: false,
+            break_duration_left: duration.try_into().unwrap(),
+            break_duration: duration.try_into().unwrap(),
+            work_goal: String::new(),
+            idle_after: None,
+            last_input: None,
+            suggestions: Rotation::default(),
+            suggestion_done: false,
+            breathing_pattern: BreathingPattern::BOX,
+            breathing: None,
+            summary: BreakSummary::default(),
+            theme: Theme::Dark,
+            ring_colors: RingColors {
+                work: Color::WHITE,
+                short_break: Color::WHITE,
+                long_break: Color::WHITE,
+                paused: Color::WHITE,
+                overtime: Color::WHITE,
+                track: Color::BLACK
+            },
+            keybindings: Keybindings::unbound(),
+            reduced_motion: false,
+            sounds: Sounds::muted()
+        }
+    }
+
+    fn tick_after(timer: &mut BreakTimer, clock: &FakeClock, duration: Duration) {
+        clock.advance(duration);
+        timer.update(BreakMessage::Tick, clock);
+    }
+
+    #[test]
+    fn counts_down_on_tick() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        clock.advance(MINUTE);
+        assert_eq!(timer.break_duration_left, SignedDuration::from_mins(5));
+        timer.update(BreakMessage::Tick, &clock);
+        assert_eq!(timer.break_duration_left, SignedDuration::from_mins(4));
+    }
+
+    #[test]
+    fn keeps_counting_past_the_end_of_the_break() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        tick_after(&mut timer, &clock, 4 * MINUTE);
+        tick_after(&mut timer, &clock, 2 * MINUTE + Duration::from_secs(30));
+        assert_eq!(timer.break_duration_left, SignedDuration::from_secs(-90));
+        tick_after(&mut timer, &clock, 60 * MINUTE);
+        assert_eq!(
+            timer.break_duration_left,
+            SignedDuration::from_secs(-61 * 60 - 30)
+        );
+    }
+
+    #[test]
+    fn ticks_without_elapsed_time_change_nothing() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        for _ in 0..100 {
+            timer.update(BreakMessage::Tick, &clock);
+        }
+
+        assert_eq!(timer.break_duration_left, SignedDuration::from_mins(5));
+    }
+
+    #[test]
+    fn continue_working_needs_a_goal() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        tick_after(&mut timer, &clock, 6 * MINUTE);
+        assert_eq!(timer.update(BreakMessage::ContinueWorking, &clock), None);
+
+        timer.update(
+            BreakMessage::WorkGoalChange("Write docs".to_string()),
+            &clock
+        );
+        assert_eq!(
+            timer.update(BreakMessage::ContinueWorking, &clock),
+            Some("Write docs".to_string())
+        );
+        assert!(timer.work_goal.is_empty());
+    }
+
+    #[test]
+    fn input_pauses_the_countdown_when_absence_is_required() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        timer.idle_after = Some(Duration::from_secs(5));
+        tick_after(&mut timer, &clock, MINUTE);
+        timer.update(BreakMessage::Input, &clock);
+        tick_after(&mut timer, &clock, Duration::from_secs(3));
+        assert!(timer.touched());
+        assert_eq!(timer.break_duration_left, SignedDuration::from_mins(4));
+
+        tick_after(&mut timer, &clock, Duration::from_secs(12));
+        assert!(!timer.touched());
+        assert_eq!(
+            timer.break_duration_left,
+            SignedDuration::from_mins(4) - SignedDuration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn input_is_ignored_unless_absence_is_required() {
+        let clock = FakeClock::new();
+        let mut timer = break_timer(&clock, 5 * MINUTE);
+        timer.update(BreakMessage::Input, &clock);
+        tick_after(&mut timer, &clock, MINUTE);
+        assert_eq!(timer.break_duration_left, SignedDuration::from_mins(4));
+    }
+}
@@ -0,0 +1,117 @@
+//! Guided breathing on the break overlay, drawn as an expanding and contracting ring
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+const PHASES: [&str; 4] = ["Breathe in", "Hold", "Breathe out", "Hold"];
+/// Size of the ring when the lungs are empty, relative to its full size
+const MIN_SIZE: f32 = 0.4;
+
+/// Durations of inhaling, holding, exhaling and holding again, never all zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreathingPattern([Duration; 4]);
+
+impl BreathingPattern {
+    pub const BOX: Self = Self::from_secs([4, 4, 4, 4]);
+    pub const FOUR_SEVEN_EIGHT: Self = Self::from_secs([4, 7, 8, 0]);
+
+    /// `None` if all the durations are zero
+    pub const fn new(phases: [Duration; 4]) -> Option<Self> {
+        let [inhale, hold, exhale, hold_out] = phases;
+        if inhale.is_zero() && hold.is_zero() && exhale.is_zero() && hold_out.is_zero() {
+            return None;
+        }
+
+        Some(Self(phases))
+    }
+
+    const fn from_secs([inhale, hold, exhale, hold_out]: [u64; 4]) -> Self {
+        Self([
+            Duration::from_secs(inhale),
+            Duration::from_secs(hold),
+            Duration::from_secs(exhale),
+            Duration::from_secs(hold_out)
+        ])
+    }
+
+    fn cycle(&self) -> Duration {
+        self.0.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Breathing {
+    pattern: BreathingPattern,
+    started: Instant
+}
+
+impl Breathing {
+    pub fn new(pattern: BreathingPattern, clock: &impl Clock) -> Self {
+        Self {
+            pattern,
+            started: clock.now()
+        }
+    }
+
+    /// What to do at `now` and the size of the ring, relative to its full size
+    pub fn at(&self, now: Instant) -> (&'static str, f32) {
+        let cycle = self.pattern.cycle();
+        let mut elapsed = Duration::from_nanos(
+            (now.saturating_duration_since(self.started).as_nanos() % cycle.as_nanos()) as u64
+        );
+
+        for (index, phase) in self.pattern.0.into_iter().enumerate() {
+            if elapsed >= phase {
+                elapsed -= phase;
+                continue;
+            }
+
+            let progress = elapsed.as_secs_f32() / phase.as_secs_f32();
+            let fullness = match index {
+                0 => progress,
+                1 => 1.0,
+                2 => 1.0 - progress,
+                _ => 0.0
+            };
+            return (PHASES[index], MIN_SIZE + (1.0 - MIN_SIZE) * fullness);
+        }
+
+        unreachable!("The elapsed time is shorter than the cycle")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    #[test]
+    fn goes_through_the_phases() {
+        let clock = FakeClock::new();
+        let breathing = Breathing::new(BreathingPattern::BOX, &clock);
+        let at = |secs| breathing.at(clock.now() + Duration::from_secs_f32(secs));
+
+        assert_eq!(at(0.0), ("Breathe in", MIN_SIZE));
+        assert_eq!(at(4.0), ("Hold", 1.0));
+        assert_eq!(at(8.0), ("Breathe out", 1.0));
+        assert_eq!(at(12.0), ("Hold", MIN_SIZE));
+        assert_eq!(at(16.0), ("Breathe in", MIN_SIZE));
+        assert_eq!(at(18.0).1, MIN_SIZE + (1.0 - MIN_SIZE) / 2.0);
+    }
+
+    #[test]
+    fn skips_empty_phases() {
+        let clock = FakeClock::new();
+        let breathing = Breathing::new(BreathingPattern::FOUR_SEVEN_EIGHT, &clock);
+
+        assert_eq!(
+            breathing.at(clock.now() + Duration::from_secs(19)).0,
+            "Breathe in"
+        );
+    }
+
+    #[test]
+    fn rejects_all_zeros() {
+        assert_eq!(BreathingPattern::new([Duration::ZERO; 4]), None);
+    }
+}
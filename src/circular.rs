@@ -2,29 +2,41 @@
 use std::f32::consts::PI;
 
 use iced::{
-    Color, Element, Event, Length, Radians, Rectangle, Renderer, Size, Theme, Vector,
+    Color, Element, Length, Radians, Rectangle, Renderer, Size, Theme, Vector,
     advanced::{
-        self, Clipboard, Layout, Shell, Widget, layout, renderer,
+        self, Layout, Widget, layout, renderer,
         widget::tree::{self, Tree}
     },
     mouse,
-    widget::canvas::{self, LineCap},
-    window
+    widget::canvas::{self, LineCap}
 };
 
 pub struct Circular {
     pub percentage: f32,
     pub color: Color,
-    pub theme: Theme
+    pub track: Color,
+    pub theme: Theme,
+    /// Radius relative to the full size of the widget, between 0 and 1
+    pub size: f32,
+    pub diameter: f32,
+    pub stroke_width: f32
 }
 
 #[derive(Default)]
 struct State {
-    cache: canvas::Cache
+    cache: canvas::Cache,
+    /// What's in the cache
+    drawn: Option<Drawn>
 }
 
-const SIZE: f32 = 400.0;
-const WIDTH: f32 = 8.0;
+#[derive(Clone, Copy, PartialEq)]
+struct Drawn {
+    percentage: f32,
+    color: Color,
+    track: Color,
+    size: f32,
+    stroke_width: f32
+}
 impl<Message> Widget<Message, Theme, Renderer> for Circular
 where
     Message: Clone
@@ -39,8 +51,8 @@ where
 
     fn size(&self) -> Size<Length> {
         Size {
-            width: Length::Fixed(SIZE),
-            height: Length::Fixed(SIZE)
+            width: Length::Fixed(self.diameter),
+            height: Length::Fixed(self.diameter)
         }
     }
 
@@ -50,25 +62,21 @@ where
         _renderer: &Renderer,
         limits: &layout::Limits
     ) -> layout::Node {
-        layout::atomic(limits, SIZE, SIZE)
+        layout::atomic(limits, self.diameter, self.diameter)
     }
 
-    fn update(
-        &mut self,
-        tree: &mut Tree,
-        event: &Event,
-        _layout: Layout<'_>,
-        _cursor: mouse::Cursor,
-        _renderer: &Renderer,
-        _clipboard: &mut dyn Clipboard,
-        shell: &mut Shell<'_, Message>,
-        _viewport: &Rectangle
-    ) {
+    fn diff(&self, tree: &mut Tree) {
         let state = tree.state.downcast_mut::<State>();
-
-        if let Event::Window(window::Event::RedrawRequested(_)) = event {
+        let shown = Some(Drawn {
+            percentage: self.percentage,
+            color: self.color,
+            track: self.track,
+            size: self.size,
+            stroke_width: self.stroke_width
+        });
+        if state.drawn != shown {
             state.cache.clear();
-            shell.request_redraw();
+            state.drawn = shown;
         }
     }
 
@@ -87,7 +95,7 @@ where
         let state = tree.state.downcast_ref::<State>();
         let bounds = layout.bounds();
         let geometry = state.cache.draw(renderer, bounds.size(), |frame| {
-            let track_radius = frame.width() / 2.0 - WIDTH;
+            let track_radius = (frame.width() / 2.0 - self.stroke_width) * self.size;
             let track_path = canvas::Path::circle(frame.center(), track_radius);
             frame.fill(
                 &track_path,
@@ -97,8 +105,8 @@ where
             frame.stroke(
                 &track_path,
                 canvas::Stroke::default()
-                    .with_color(self.theme.extended_palette().background.weak.color)
-                    .with_width(WIDTH)
+                    .with_color(self.track)
+                    .with_width(self.stroke_width)
             );
 
             let mut builder = canvas::path::Builder::new();
@@ -116,7 +124,7 @@ where
                 &bar_path,
                 canvas::Stroke::default()
                     .with_color(self.color)
-                    .with_width(WIDTH)
+                    .with_width(self.stroke_width)
                     .with_line_cap(LineCap::Round)
             );
         });
@@ -1,4 +1,6 @@
-use clap::{ArgAction, Parser};
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -6,5 +8,34 @@ pub struct Cli {
     #[arg(short = 'p', long, action = ArgAction::SetTrue, exclusive = true)]
     pub print_default_config: bool,
     #[arg(short = 'w', long, action = ArgAction::SetTrue, exclusive = true)]
-    pub write_default_config: bool
+    pub write_default_config: bool,
+    /// Use an alternative config file
+    #[arg(short, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Override a config key, e.g. `pomodoro.work_duration.mins=50`
+    #[arg(short, long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    pub overrides: Vec<(String, String)>,
+    /// Start even if another instance is running instead of raising its window. The new instance
+    /// then doesn't serve the D-Bus interface, which stays with the running one
+    #[arg(long)]
+    pub force_new: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum Command {
+    /// Run the work/break cycle without a main window, attach one with `annoyodoro ui`
+    Daemon,
+    /// Attach a main window to the running daemon
+    Ui,
+    /// Run in the terminal, without the main window and the break overlay
+    Tui
+}
+
+fn parse_override(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{arg}`"))?;
+    Ok((key.trim().to_string(), value.trim().to_string()))
 }
@@ -0,0 +1,40 @@
+//! Where the timers get the current time from, so that they can be tested without waiting
+use std::time::Instant;
+#[cfg(test)]
+use std::{cell::Cell, time::Duration};
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Only moves when it's told to
+#[cfg(test)]
+#[derive(Debug)]
+pub struct FakeClock(Cell<Instant>);
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new() -> Self {
+        Self(Cell::new(Instant::now()))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
@@ -0,0 +1,67 @@
+//! The light or dark preference of the desktop, from the freedesktop settings portal
+use iced::{
+    futures::{SinkExt, Stream, StreamExt, channel::mpsc::Sender},
+    stream
+};
+use zbus::{Connection, zvariant::OwnedValue};
+
+const NAMESPACE: &str = "org.freedesktop.appearance";
+const KEY: &str = "color-scheme";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    NoPreference,
+    Dark,
+    Light
+}
+
+impl From<u32> for ColorScheme {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Dark,
+            2 => Self::Light,
+            _ => Self::NoPreference
+        }
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop",
+    gen_blocking = false
+)]
+trait Settings {
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<OwnedValue>;
+
+    #[zbus(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: OwnedValue) -> zbus::Result<()>;
+}
+
+/// The current preference, then its changes
+pub fn run() -> impl Stream<Item = ColorScheme> {
+    stream::channel(1, async |mut schemes: Sender<ColorScheme>| {
+        if let Err(err) = watch(&mut schemes).await {
+            eprintln!("Cannot read the color scheme from the desktop portal: {err}");
+        }
+    })
+}
+
+async fn watch(schemes: &mut Sender<ColorScheme>) -> zbus::Result<()> {
+    let connection = Connection::session().await?;
+    let settings = SettingsProxy::new(&connection).await?;
+    let mut changes = settings.receive_setting_changed().await?;
+    let scheme = u32::try_from(settings.read_one(NAMESPACE, KEY).await?)?;
+    let _ = schemes.send(scheme.into()).await;
+
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        if args.namespace == NAMESPACE && args.key == KEY {
+            let scheme = u32::try_from(args.value)?;
+            let _ = schemes.send(scheme.into()).await;
+        }
+    }
+
+    Ok(())
+}
@@ -1,19 +1,62 @@
-use std::{fs, num::NonZero, path::PathBuf, time::Duration};
+use std::{
+    env, fs,
+    num::NonZero,
+    path::{Path, PathBuf},
+    time::Duration
+};
 
 use figment::{
     Figment,
-    providers::{Data, Toml}
+    providers::{Data, Toml},
+    value::Value
+};
+use iced::{Color, Font, Size, Theme, theme::Palette};
+use serde::{
+    Deserialize, Deserializer,
+    de::{Error, Visitor, value},
+    forward_to_deserialize_any
+};
+use yanet::{Result, ResultExt};
+
+use crate::{
+    breathing::BreathingPattern,
+    color_scheme::ColorScheme,
+    keybindings::Keybindings,
+    themes::{self, Colors},
+    view::Metrics
 };
-use iced::{Color, Font, Theme, theme::Palette};
-use serde::{Deserialize, Deserializer, de::Error};
-use yanet::Result;
 
 #[derive(Clone, Copy, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(deserialize_with = "deserialize_font")]
     pub font: Font,
+    /// How often the progress rings are redrawn while they're shown
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub ring_update_interval: Duration,
     pub pomodoro: PomodoroConfig,
+    pub keybindings: Keybindings,
+    pub hooks: HooksConfig,
+    pub media: MediaConfig,
+    pub sounds: SoundsConfig,
+    pub suggestions: SuggestionsConfig,
+    pub breathing: BreathingConfig,
+    pub micro_breaks: MicroBreaksConfig,
+    pub absence: AbsenceConfig,
+    pub layout: LayoutConfig,
+    pub accessibility: AccessibilityConfig,
+    #[serde(deserialize_with = "deserialize_preset")]
+    theme: Colors,
+    /// Replaces `theme` while the desktop prefers a light color scheme
+    #[serde(default, deserialize_with = "deserialize_optional_preset")]
+    light_theme: Option<Colors>,
+    /// Replaces `theme` while the desktop prefers a dark color scheme
+    #[serde(default, deserialize_with = "deserialize_optional_preset")]
+    dark_theme: Option<Colors>,
+    /// Followed at runtime, not configured
+    #[serde(skip)]
+    color_scheme: ColorScheme,
+    /// Overrides the colors of the theme
     colors: ColorsConfig
 }
 
@@ -36,49 +79,369 @@ pub struct PomodoroConfig {
     pub long_break_duration: Duration,
     #[serde(deserialize_with = "deserialize_duration")]
     pub notification_duration: Duration,
-    pub long_break_each: NonZero<u16>
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub snooze_duration: Duration,
+    pub long_break_each: NonZero<u16>,
+    /// Pomodori a day, 0 for no target
+    pub daily_target: u32
+}
+
+impl PomodoroConfig {
+    pub fn break_duration(&self, long_break: bool) -> Duration {
+        if long_break {
+            self.long_break_duration
+        } else {
+            self.break_duration
+        }
+    }
+}
+
+/// Leaked so that the config stays `Copy`
+pub type ShellCommand = &'static str;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub work_start: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub pause: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub resume: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub pre_break_notification: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub break_start: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub break_end: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub goal_submitted: Option<ShellCommand>,
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub session_end: Option<ShellCommand>
+}
+
+fn deserialize_command<'de, D>(deserializer: D) -> Result<Option<ShellCommand>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let command = String::deserialize(deserializer)?;
+    Ok((!command.trim().is_empty()).then(|| &*command.leak()))
+}
+
+/// Leaked so that the config stays `Copy`
+pub type Identities = &'static [&'static str];
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MediaConfig {
+    pub pause: bool,
+    #[serde(deserialize_with = "deserialize_identities")]
+    pub allow: Identities,
+    #[serde(deserialize_with = "deserialize_identities")]
+    pub deny: Identities,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub fade: Duration
+}
+
+fn deserialize_identities<'de, D>(deserializer: D) -> Result<Identities, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let identities = Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|identity| &*identity.leak())
+        .collect::<Vec<_>>()
+        .leak();
+    Ok(identities)
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoundsConfig {
+    pub enabled: bool,
+    pub pre_break: SoundConfig,
+    pub break_start: SoundConfig,
+    pub break_end: SoundConfig,
+    pub session_end: SoundConfig
+}
+
+/// Leaked so that the config stays `Copy`
+pub type SoundFile = &'static Path;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoundConfig {
+    /// Replaces the bundled sound
+    #[serde(default, deserialize_with = "deserialize_file")]
+    pub file: Option<SoundFile>,
+    pub volume: f32
+}
+
+/// An empty path is no file, like when it isn't set
+fn deserialize_file<'de, D>(deserializer: D) -> Result<Option<&'static Path>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let path = PathBuf::deserialize(deserializer)?;
+    Ok((!path.as_os_str().is_empty()).then(|| &*Box::leak(path.into_boxed_path())))
+}
+
+/// Leaked so that the config stays `Copy`
+pub type SuggestionsFile = &'static Path;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SuggestionsConfig {
+    pub enabled: bool,
+    /// Adds to the built-in suggestions
+    #[serde(default, deserialize_with = "deserialize_file")]
+    pub file: Option<SuggestionsFile>
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreathingConfig {
+    /// Whether breaks start with the breathing guide instead of the countdown
+    pub enabled: bool,
+    #[serde(deserialize_with = "deserialize_breathing_pattern")]
+    pub pattern: BreathingPattern
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MicroBreaksConfig {
+    pub enabled: bool,
+    /// Work time between micro-breaks
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub duration: Duration,
+    /// Whether a micro-break can be ended before its time is up
+    pub dismissable: bool
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbsenceConfig {
+    /// Whether breaks only count down while the machine isn't touched
+    pub required: bool,
+    /// How long after the last input the break counts down again
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub idle_after: Duration
+}
+
+impl AbsenceConfig {
+    /// `None` if absence isn't required
+    pub fn idle_after(&self) -> Option<Duration> {
+        self.required.then_some(self.idle_after)
+    }
 }
 
 #[derive(Deserialize)]
+#[serde(untagged)]
+enum BreathingPatternConfig {
+    Named(String),
+    Secs([u64; 4])
+}
+
+fn deserialize_breathing_pattern<'de, D>(deserializer: D) -> Result<BreathingPattern, D::Error>
+where
+    D: Deserializer<'de>
+{
+    match BreathingPatternConfig::deserialize(deserializer)? {
+        BreathingPatternConfig::Named(name) => match name.as_str() {
+            "box" => Ok(BreathingPattern::BOX),
+            "4-7-8" => Ok(BreathingPattern::FOUR_SEVEN_EIGHT),
+            _ => Err(Error::unknown_variant(&name, &["box", "4-7-8"]))
+        },
+        BreathingPatternConfig::Secs(secs) => BreathingPattern::new(secs.map(Duration::from_secs))
+            .ok_or_else(|| Error::custom("a breathing pattern cannot be all zeros"))
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
 #[serde(deny_unknown_fields)]
-struct SecsAndMins {
+pub struct AccessibilityConfig {
+    /// Whether the rings are only redrawn once a second and the breathing ring stays still
+    pub reduced_motion: bool,
+    /// Run with the announcement of the remaining time appended
+    #[serde(default, deserialize_with = "deserialize_command")]
+    pub announce_command: Option<ShellCommand>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub announce_interval: Duration
+}
+
+/// Leaked so that the config stays `Copy`
+pub type BackgroundImage = &'static Path;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Whether the sizes on the break overlay are scaled to the height of its output
+    pub auto_scale: bool,
+    pub ring_size: f32,
+    pub ring_width: f32,
+    pub timer_text_size: f32,
+    pub text_size: f32,
+    pub spacing: f32,
+    /// Of the background of the break and micro-break overlays
+    pub overlay_opacity: f32,
+    /// Drawn beneath the background of the break and micro-break overlays
+    #[serde(default, deserialize_with = "deserialize_file")]
+    pub overlay_background: Option<BackgroundImage>
+}
+
+impl LayoutConfig {
+    /// Height of the output the sizes are meant for
+    const REFERENCE_HEIGHT: f32 = 1080.0;
+
+    /// The sizes for an output of `output_size`, unscaled while it's unknown
+    pub fn metrics(&self, output_size: Option<Size>) -> Metrics {
+        let scale = match output_size {
+            Some(size) if self.auto_scale => (size.height / Self::REFERENCE_HEIGHT).clamp(0.5, 4.0),
+            _ => 1.0
+        };
+
+        Metrics {
+            scale,
+            ring_size: self.ring_size * scale,
+            ring_width: self.ring_width * scale,
+            timer_text_size: self.timer_text_size * scale,
+            text_size: self.text_size * scale,
+            spacing: self.spacing * scale
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DurationUnits {
     #[serde(default)]
     mins: u64,
     #[serde(default)]
-    secs: u64
+    secs: u64,
+    #[serde(default)]
+    millis: u64
 }
 
 fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>
 {
-    let secs_and_mins = SecsAndMins::deserialize(deserializer)?;
-    let secs = secs_and_mins.secs + secs_and_mins.mins * 60;
-    Ok(Duration::from_secs(secs))
+    let DurationUnits { mins, secs, millis } = DurationUnits::deserialize(deserializer)?;
+    Ok(Duration::from_secs(secs + mins * 60) + Duration::from_millis(millis))
+}
+
+fn deserialize_preset<'de, D>(deserializer: D) -> Result<Colors, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let name = String::deserialize(deserializer)?;
+    themes::preset(&name).ok_or_else(|| Error::unknown_variant(&name, themes::NAMES))
+}
+
+fn deserialize_optional_preset<'de, D>(deserializer: D) -> Result<Option<Colors>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    deserialize_preset(deserializer).map(Some)
 }
 
 #[derive(Deserialize, Clone, Copy)]
 #[serde(deny_unknown_fields)]
 struct ColorsConfig {
-    #[serde(deserialize_with = "deserialize_color")]
-    background: Color,
-    #[serde(deserialize_with = "deserialize_color")]
-    text: Color,
-    #[serde(deserialize_with = "deserialize_color")]
-    accent: Color,
-    #[serde(deserialize_with = "deserialize_color")]
-    danger: Color
+    #[serde(default, deserialize_with = "deserialize_color")]
+    background: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    text: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    accent: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    danger: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    work_ring: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    short_break_ring: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    long_break_ring: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    paused_ring: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    overtime_ring: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    ring_track: Option<Color>
+}
+
+/// Colors of the progress rings and their countdowns in each state
+#[derive(Debug, Clone, Copy)]
+pub struct RingColors {
+    pub work: Color,
+    pub short_break: Color,
+    pub long_break: Color,
+    pub paused: Color,
+    /// A break that's over while the next work goal isn't submitted yet
+    pub overtime: Color,
+    pub track: Color
+}
+
+impl RingColors {
+    pub fn for_break(&self, long_break: bool) -> Color {
+        if long_break {
+            self.long_break
+        } else {
+            self.short_break
+        }
+    }
 }
 
-fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+/// An empty color follows the theme, like when it isn't set
+fn deserialize_color<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
 where
     D: Deserializer<'de>
 {
-    let csscolorparser::Color { r, g, b, a } = String::deserialize(deserializer)?
-        .parse()
-        .map_err(Error::custom)?;
+    let color = String::deserialize(deserializer)?;
+    if color.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let csscolorparser::Color { r, g, b, a } = color.parse().map_err(Error::custom)?;
 
-    Ok(Color { r, g, b, a })
+    Ok(Some(Color { r, g, b, a }))
+}
+
+/// The fields of `T`, as its derived `Deserialize` passes them to the deserializer
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Fields<'_> {
+        type Error = value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(Error::custom("only the fields are read"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    let mut fields = &[][..];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
 }
 
 impl Config {
@@ -113,22 +476,91 @@ impl Config {
         Ok(())
     }
 
-    pub fn new() -> Result<Self> {
-        let path = Self::path()?;
-        let config = Figment::new()
+    /// Layers the default config, the config file (`path` or the default location), `ANNOYODORO_`
+    /// environment variables and `overrides` in this order
+    pub fn new(path: Option<PathBuf>, overrides: &[(String, String)]) -> Result<Self> {
+        let file = match path {
+            Some(path) => {
+                fs::metadata(&path)
+                    .wrap_err_with(|_| format!("Cannot open {}", path.to_string_lossy()))?;
+                Data::<Toml>::file_exact(path)
+            }
+            None => Data::<Toml>::file(Self::path()?)
+        };
+
+        Self::layered(file, &Self::env_overrides(env::vars()), overrides)
+    }
+
+    /// The `ANNOYODORO_` variables starting with a top-level key as overrides, nested keys are
+    /// separated by `__`. The others, like the `ANNOYODORO_HOOK_` ones passed to hooks, aren't meant
+    /// for the config
+    fn env_overrides(vars: impl IntoIterator<Item = (String, String)>) -> Vec<(String, String)> {
+        let keys = struct_fields::<Self>();
+        vars.into_iter()
+            .filter_map(|(key, value)| {
+                let key = key.strip_prefix("ANNOYODORO_")?.to_lowercase();
+                let top_level = key.split("__").next()?;
+                keys.contains(&top_level)
+                    .then(|| (key.replace("__", "."), value))
+            })
+            .collect()
+    }
+
+    fn layered(
+        file: Data<Toml>,
+        env_overrides: &[(String, String)],
+        overrides: &[(String, String)]
+    ) -> Result<Self> {
+        let figment = Figment::new()
             .merge(Data::<Toml>::string(Self::DEFAULT))
-            .merge(Data::<Toml>::file(path))
+            .merge(file);
+        let config = env_overrides
+            .iter()
+            .chain(overrides)
+            .fold(figment, |figment, (key, value)| {
+                let value: Value = value
+                    .parse()
+                    .expect("Parsing a figment value is infallible");
+                figment.merge((key, value))
+            })
             .extract()?;
         Ok(config)
     }
 
+    /// Whether the theme depends on the color scheme preferred by the desktop
+    pub fn follows_color_scheme(&self) -> bool {
+        self.light_theme.is_some() || self.dark_theme.is_some()
+    }
+
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+
+    /// The preset for the current color scheme with the overrides applied
+    fn colors(&self) -> Colors {
+        let preset = match self.color_scheme {
+            ColorScheme::Light => self.light_theme,
+            ColorScheme::Dark => self.dark_theme,
+            ColorScheme::NoPreference => None
+        }
+        .unwrap_or(self.theme);
+        let colors = self.colors;
+
+        Colors {
+            background: colors.background.unwrap_or(preset.background),
+            text: colors.text.unwrap_or(preset.text),
+            accent: colors.accent.unwrap_or(preset.accent),
+            danger: colors.danger.unwrap_or(preset.danger)
+        }
+    }
+
     pub fn theme(&self) -> Theme {
-        let ColorsConfig {
+        let Colors {
             background,
             text,
             accent,
             danger
-        } = self.colors;
+        } = self.colors();
 
         Theme::custom(
             String::new(),
@@ -142,4 +574,117 @@ impl Config {
             }
         )
     }
+
+    /// The ones that aren't configured follow the theme
+    pub fn ring_colors(&self) -> RingColors {
+        let Colors { accent, danger, .. } = self.colors();
+        let colors = self.colors;
+
+        RingColors {
+            work: colors.work_ring.unwrap_or(accent),
+            short_break: colors.short_break_ring.unwrap_or(accent),
+            long_break: colors.long_break_ring.unwrap_or(accent),
+            paused: colors.paused_ring.unwrap_or(accent),
+            overtime: colors.overtime_ring.unwrap_or(danger),
+            track: colors
+                .ring_track
+                .unwrap_or(self.theme().extended_palette().background.weak.color)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(file: &str, env: &[(&str, &str)], overrides: &[(&str, &str)]) -> Config {
+        let owned = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        Config::layered(
+            Data::<Toml>::string(file),
+            &Config::env_overrides(owned(env)),
+            &owned(overrides)
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn layers_defaults_file_env_and_overrides() {
+        let config = config(
+            "[pomodoro]\nwork_duration.mins = 25\nbreak_duration.mins = 6\nlong_break_each = 2",
+            &[
+                ("ANNOYODORO_POMODORO__WORK_DURATION__MINS", "30"),
+                ("ANNOYODORO_POMODORO__BREAK_DURATION__MINS", "10")
+            ],
+            &[("pomodoro.break_duration.mins", "7")]
+        );
+        let pomodoro = config.pomodoro;
+        assert_eq!(pomodoro.long_break_duration, Duration::from_secs(25 * 60));
+        assert_eq!(pomodoro.long_break_each.get(), 2);
+        assert_eq!(pomodoro.work_duration, Duration::from_secs(30 * 60));
+        assert_eq!(pomodoro.break_duration, Duration::from_secs(7 * 60));
+    }
+
+    #[test]
+    fn ignores_variables_not_meant_for_the_config() {
+        let config = config(
+            "",
+            &[
+                ("ANNOYODORO_UNRELATED", "1"),
+                ("ANNOYODORO_HOOK_GOAL", "Write tests"),
+                ("ANNOYODORO_FONT", "monospace"),
+                ("PATH", "/usr/bin")
+            ],
+            &[]
+        );
+        assert_eq!(config.font, Font::with_name("monospace"));
+    }
+
+    #[test]
+    fn empty_sound_files_are_unset() {
+        let config = config(
+            "[sounds]\npre_break.file = \"\"\nbreak_end.file = \"bell.ogg\"",
+            &[],
+            &[]
+        );
+        assert_eq!(config.sounds.pre_break.file, None);
+        assert_eq!(config.sounds.break_end.file, Some(Path::new("bell.ogg")));
+    }
+
+    #[test]
+    fn an_empty_suggestions_file_is_unset() {
+        let config = config("[suggestions]\nfile = \"\"", &[], &[]);
+        assert_eq!(config.suggestions.file, None);
+    }
+
+    #[test]
+    fn an_empty_overlay_background_is_unset() {
+        let config = config("[layout]\noverlay_background = \"\"", &[], &[]);
+        assert_eq!(config.layout.overlay_background, None);
+    }
+
+    #[test]
+    fn empty_ring_colors_follow_the_theme() {
+        let config = config(
+            "theme = \"nord\"\n[colors]\nwork_ring = \"\"\npaused_ring = \"#ff0000\"",
+            &[],
+            &[]
+        );
+        let ring_colors = config.ring_colors();
+        assert_eq!(ring_colors.work, themes::preset("nord").unwrap().accent);
+        assert_eq!(ring_colors.paused, Color::from_rgb(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reads_the_fields_of_the_config() {
+        let fields = struct_fields::<Config>();
+        assert!(fields.contains(&"pomodoro"));
+        assert!(fields.contains(&"dark_theme"));
+        assert!(!fields.contains(&"color_scheme"));
+    }
 }
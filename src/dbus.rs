@@ -0,0 +1,375 @@
+//! `org.annoyodoro.Timer` D-Bus interface of the running instance
+use std::{
+    fmt::{self, Debug},
+    mem
+};
+
+use iced::{
+    futures::{SinkExt, Stream, StreamExt, channel::mpsc::Sender},
+    stream
+};
+use smol::channel;
+use zbus::{
+    Connection, connection, fdo, interface, object_server::InterfaceRef, proxy::CacheProperties
+};
+
+pub const BUS_NAME: &str = "org.annoyodoro.Timer";
+pub const OBJECT_PATH: &str = "/org/annoyodoro/Timer";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Phase {
+    #[default]
+    WaitingForGoal,
+    Work,
+    Break
+}
+
+impl Phase {
+    const ALL: [Self; 3] = [Self::WaitingForGoal, Self::Work, Self::Break];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Phase::WaitingForGoal => "waiting_for_goal",
+            Phase::Work => "work",
+            Phase::Break => "break"
+        }
+    }
+}
+
+/// What the interface exposes as properties
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TimerState {
+    pub phase: Phase,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    pub last_session: bool,
+    pub long_break_in: u16,
+    pub pomodori_today: u32,
+    pub current_goal: String
+}
+
+#[derive(Debug, Clone)]
+pub enum DbusCommand {
+    Pause,
+    Resume,
+    StartBreak,
+    SetLastSession(bool),
+    SubmitGoal(String),
+    ShowWindow
+}
+
+#[derive(Debug, Clone)]
+pub enum DbusEvent {
+    Ready(DbusHandle),
+    Command(DbusCommand)
+}
+
+struct TimerInterface {
+    state: TimerState,
+    events: Sender<DbusEvent>
+}
+
+impl TimerInterface {
+    async fn send(&self, command: DbusCommand) -> fdo::Result<()> {
+        self.events
+            .clone()
+            .send(DbusEvent::Command(command))
+            .await
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    fn expect_phase(&self, phase: Phase) -> fdo::Result<()> {
+        if self.state.phase != phase {
+            return Err(fdo::Error::Failed(format!(
+                "Expected phase {}, the timer is in phase {}",
+                phase.as_str(),
+                self.state.phase.as_str()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[interface(name = "org.annoyodoro.Timer")]
+impl TimerInterface {
+    async fn pause(&self) -> fdo::Result<()> {
+        self.expect_phase(Phase::Work)?;
+        self.send(DbusCommand::Pause).await
+    }
+
+    async fn resume(&self) -> fdo::Result<()> {
+        self.expect_phase(Phase::Work)?;
+        self.send(DbusCommand::Resume).await
+    }
+
+    async fn start_break(&self) -> fdo::Result<()> {
+        self.expect_phase(Phase::Work)?;
+        self.send(DbusCommand::StartBreak).await
+    }
+
+    async fn set_last_session(&self, last_session: bool) -> fdo::Result<()> {
+        self.expect_phase(Phase::Work)?;
+        self.send(DbusCommand::SetLastSession(last_session)).await
+    }
+
+    /// Only the goal of the first work session can be submitted for now, the later ones are
+    /// submitted on the break overlay
+    async fn submit_goal(&self, goal: String) -> fdo::Result<()> {
+        self.expect_phase(Phase::WaitingForGoal)?;
+        if goal.trim().is_empty() {
+            return Err(fdo::Error::InvalidArgs("The goal is empty".to_string()));
+        }
+
+        self.send(DbusCommand::SubmitGoal(goal)).await
+    }
+
+    /// Opens the main window or focuses it if it's already open
+    async fn show_window(&self) -> fdo::Result<()> {
+        self.send(DbusCommand::ShowWindow).await
+    }
+
+    /// Seconds until the next break
+    #[zbus(property)]
+    fn remaining(&self) -> u64 {
+        self.state.remaining_secs
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// One of `waiting_for_goal`, `work` and `break`
+    #[zbus(property)]
+    fn phase(&self) -> &str {
+        self.state.phase.as_str()
+    }
+
+    #[zbus(property)]
+    fn last_session(&self) -> bool {
+        self.state.last_session
+    }
+
+    #[zbus(property)]
+    fn long_break_in(&self) -> u16 {
+        self.state.long_break_in
+    }
+
+    #[zbus(property)]
+    fn pomodori_today(&self) -> u32 {
+        self.state.pomodori_today
+    }
+
+    #[zbus(property)]
+    fn current_goal(&self) -> &str {
+        &self.state.current_goal
+    }
+}
+
+/// The states are published by [`run`] one after another, so that the signals are in order
+#[derive(Clone)]
+pub struct DbusHandle {
+    states: channel::Sender<TimerState>,
+    shown: TimerState
+}
+
+impl Debug for DbusHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbusHandle")
+            .field("shown", &self.shown)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DbusHandle {
+    /// Only emits `PropertiesChanged` for the properties that differ from what's shown
+    pub fn update(&mut self, state: TimerState) {
+        if self.shown == state {
+            return;
+        }
+
+        // The channel is only closed along with the connection
+        let _ = self.states.try_send(state.clone());
+        self.shown = state;
+    }
+}
+
+async fn publish(interface: &InterfaceRef<TimerInterface>, state: TimerState) {
+    let emitter = interface.signal_emitter();
+    let mut timer = interface.get_mut().await;
+    let old = mem::replace(&mut timer.state, state);
+    let new = &timer.state;
+    let result = async {
+        if old.remaining_secs != new.remaining_secs {
+            timer.remaining_changed(emitter).await?;
+        }
+
+        if old.paused != new.paused {
+            timer.paused_changed(emitter).await?;
+        }
+
+        if old.last_session != new.last_session {
+            timer.last_session_changed(emitter).await?;
+        }
+
+        if old.phase != new.phase {
+            timer.phase_changed(emitter).await?;
+        }
+
+        if old.long_break_in != new.long_break_in {
+            timer.long_break_in_changed(emitter).await?;
+        }
+
+        if old.pomodori_today != new.pomodori_today {
+            timer.pomodori_today_changed(emitter).await?;
+        }
+
+        if old.current_goal != new.current_goal {
+            timer.current_goal_changed(emitter).await?;
+        }
+
+        zbus::Result::Ok(())
+    }
+    .await;
+
+    if let Err(err) = result {
+        eprintln!("Cannot emit D-Bus property changes: {err}");
+    }
+}
+
+async fn serve(
+    events: Sender<DbusEvent>
+) -> zbus::Result<(Connection, InterfaceRef<TimerInterface>)> {
+    let interface = TimerInterface {
+        state: TimerState::default(),
+        events
+    };
+
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, interface)?
+        .build()
+        .await?;
+    let interface = connection.object_server().interface(OBJECT_PATH).await?;
+    Ok((connection, interface))
+}
+
+/// Serves the interface on the session bus, if that fails the stream never produces anything
+pub fn run() -> impl Stream<Item = DbusEvent> {
+    stream::channel(10, async |mut events: Sender<DbusEvent>| {
+        match serve(events.clone()).await {
+            // Dropping the connection would unregister the interface
+            Ok((_connection, interface)) => {
+                let (states_tx, states) = channel::unbounded();
+                let handle = DbusHandle {
+                    states: states_tx,
+                    shown: TimerState::default()
+                };
+                let _ = events.send(DbusEvent::Ready(handle)).await;
+                while let Ok(mut state) = states.recv().await {
+                    // Only the latest state matters if publishing lags behind
+                    while let Ok(newer) = states.try_recv() {
+                        state = newer;
+                    }
+
+                    publish(&interface, state).await;
+                }
+            }
+            Err(err) => eprintln!("Cannot serve the D-Bus interface: {err}")
+        }
+    })
+}
+
+#[zbus::proxy(
+    interface = "org.annoyodoro.Timer",
+    default_service = "org.annoyodoro.Timer",
+    default_path = "/org/annoyodoro/Timer",
+    gen_blocking = false
+)]
+pub trait Timer {
+    fn pause(&self) -> zbus::Result<()>;
+    fn resume(&self) -> zbus::Result<()>;
+    fn start_break(&self) -> zbus::Result<()>;
+    fn set_last_session(&self, last_session: bool) -> zbus::Result<()>;
+    fn submit_goal(&self, goal: &str) -> zbus::Result<()>;
+    fn show_window(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn remaining(&self) -> zbus::Result<u64>;
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn last_session(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn phase(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn long_break_in(&self) -> zbus::Result<u16>;
+    #[zbus(property)]
+    fn pomodori_today(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn current_goal(&self) -> zbus::Result<String>;
+}
+
+#[derive(Debug, Clone)]
+pub enum RemoteEvent {
+    Attached(TimerProxy<'static>),
+    State(TimerState),
+    Detached(String)
+}
+
+async fn fetch(timer: &TimerProxy<'_>) -> zbus::Result<TimerState> {
+    let phase = timer.phase().await?;
+    Ok(TimerState {
+        phase: Phase::ALL
+            .into_iter()
+            .find(|known| known.as_str() == phase)
+            .unwrap_or_default(),
+        remaining_secs: timer.remaining().await?,
+        paused: timer.paused().await?,
+        last_session: timer.last_session().await?,
+        long_break_in: timer.long_break_in().await?,
+        pomodori_today: timer.pomodori_today().await?,
+        current_goal: timer.current_goal().await?
+    })
+}
+
+async fn mirror(events: &mut Sender<RemoteEvent>) -> zbus::Result<()> {
+    let connection = Connection::session().await?;
+    // Fetched on every change instead, the cache could lag behind the change signal
+    let timer = TimerProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let properties = fdo::PropertiesProxy::builder(&connection)
+        .destination(BUS_NAME)?
+        .path(OBJECT_PATH)?
+        .build()
+        .await?;
+    let mut changes = properties.receive_properties_changed().await?;
+    let mut owner_changes = timer.inner().receive_owner_changed().await?;
+    let _ = events.send(RemoteEvent::Attached(timer.clone())).await;
+
+    loop {
+        let _ = events.send(RemoteEvent::State(fetch(&timer).await?)).await;
+        let changed = smol::future::or(async { changes.next().await.is_some() }, async {
+            owner_changes.next().await.is_some()
+        })
+        .await;
+
+        if !changed {
+            return Err(zbus::Error::Failure(
+                "The D-Bus connection was closed".to_string()
+            ));
+        }
+    }
+}
+
+/// Mirrors the state of the running instance, until it exits
+pub fn attach() -> impl Stream<Item = RemoteEvent> {
+    stream::channel(10, async |mut events: Sender<RemoteEvent>| {
+        if let Err(err) = mirror(&mut events).await {
+            let _ = events.send(RemoteEvent::Detached(err.to_string())).await;
+        }
+    })
+}
@@ -0,0 +1,79 @@
+//! How breaks are enforced, wlr-layer-shell isn't available on X11 and on some Wayland compositors
+use std::fmt::{self, Display};
+
+use wayland_client::{
+    ConnectError, Connection, Dispatch, QueueHandle,
+    protocol::wl_registry::{self, WlRegistry}
+};
+use yanet::Result;
+
+const LAYER_SHELL: &str = "zwlr_layer_shell_v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enforcement {
+    /// An overlay above every other surface, including panels
+    LayerShell,
+    /// A fullscreen always-on-top window, which the window manager may let the user escape
+    Window
+}
+
+impl Display for Enforcement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Enforcement::LayerShell => "Layer-shell overlay",
+            Enforcement::Window => "Fullscreen window (no layer-shell)"
+        })
+    }
+}
+
+impl Enforcement {
+    pub fn detect() -> Self {
+        match has_layer_shell() {
+            Ok(true) => Self::LayerShell,
+            Ok(false) => Self::Window,
+            Err(err) => {
+                eprintln!(
+                    "Cannot query the Wayland compositor, breaks use a fullscreen window: {err}"
+                );
+                Self::Window
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Globals {
+    layer_shell: bool
+}
+
+impl Dispatch<WlRegistry, ()> for Globals {
+    fn event(
+        globals: &mut Self,
+        _registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _connection: &Connection,
+        _queue: &QueueHandle<Self>
+    ) {
+        if let wl_registry::Event::Global { interface, .. } = event
+            && interface == LAYER_SHELL
+        {
+            globals.layer_shell = true;
+        }
+    }
+}
+
+fn has_layer_shell() -> Result<bool> {
+    let connection = match Connection::connect_to_env() {
+        Ok(connection) => connection,
+        // Not a Wayland session
+        Err(ConnectError::NoCompositor) => return Ok(false),
+        Err(err) => return Err(err.into())
+    };
+
+    let mut queue = connection.new_event_queue();
+    let _registry = connection.display().get_registry(&queue.handle(), ());
+    let mut globals = Globals::default();
+    queue.roundtrip(&mut globals)?;
+    Ok(globals.layer_shell)
+}
@@ -0,0 +1,136 @@
+//! User shell commands run on timer events
+use std::{io, process::Stdio, time::Duration};
+
+use serde::Serialize;
+use smol::{Timer, future, io::AsyncWriteExt, process::Command};
+use yanet::Result;
+
+use crate::config::{HooksConfig, ShellCommand};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    WorkStart,
+    Pause,
+    Resume,
+    PreBreakNotification,
+    BreakStart,
+    BreakEnd,
+    GoalSubmitted,
+    SessionEnd
+}
+
+/// Passed to the hook as JSON on stdin and as `ANNOYODORO_HOOK_*` environment variables
+#[derive(Debug, Serialize)]
+pub struct HookData {
+    pub event: HookEvent,
+    pub goal: String,
+    pub work_duration_secs: u64,
+    pub work_remaining_secs: u64,
+    pub long_break_in: u16,
+    pub pomodori_today: u32,
+    /// Only set for break events
+    pub long_break: Option<bool>,
+    /// Only set for break events
+    pub break_duration_secs: Option<u64>
+}
+
+impl HooksConfig {
+    fn command(&self, event: HookEvent) -> Option<ShellCommand> {
+        match event {
+            HookEvent::WorkStart => self.work_start,
+            HookEvent::Pause => self.pause,
+            HookEvent::Resume => self.resume,
+            HookEvent::PreBreakNotification => self.pre_break_notification,
+            HookEvent::BreakStart => self.break_start,
+            HookEvent::BreakEnd => self.break_end,
+            HookEvent::GoalSubmitted => self.goal_submitted,
+            HookEvent::SessionEnd => self.session_end
+        }
+    }
+
+    /// Runs the hook for `data.event` in the background
+    pub fn run(&self, data: HookData) {
+        smol::spawn(self.run_until_done(data)).detach();
+    }
+
+    /// Runs the hook for `data.event` until it exits or times out, failures are only reported on
+    /// stderr
+    pub async fn run_until_done(self, data: HookData) {
+        let Some(command) = self.command(data.event) else {
+            return;
+        };
+
+        if let Err(err) = run_command(command, &data, self.timeout).await {
+            eprintln!("Hook `{command}` for {:?} failed: {err}", data.event);
+        }
+    }
+}
+
+async fn run_command(command: &str, data: &HookData, timeout: Duration) -> Result<()> {
+    let event = serde_json::to_value(data.event)?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("ANNOYODORO_HOOK_EVENT", event.as_str().unwrap_or_default())
+        .env("ANNOYODORO_HOOK_GOAL", &data.goal)
+        .env(
+            "ANNOYODORO_HOOK_WORK_DURATION_SECS",
+            data.work_duration_secs.to_string()
+        )
+        .env(
+            "ANNOYODORO_HOOK_WORK_REMAINING_SECS",
+            data.work_remaining_secs.to_string()
+        )
+        .env(
+            "ANNOYODORO_HOOK_LONG_BREAK_IN",
+            data.long_break_in.to_string()
+        )
+        .env(
+            "ANNOYODORO_HOOK_POMODORI_TODAY",
+            data.pomodori_today.to_string()
+        )
+        .envs(
+            data.long_break
+                .map(|long_break| ("ANNOYODORO_HOOK_LONG_BREAK", long_break.to_string()))
+        )
+        .envs(
+            data.break_duration_secs
+                .map(|secs| ("ANNOYODORO_HOOK_BREAK_DURATION_SECS", secs.to_string()))
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take();
+    let input = serde_json::to_vec(data)?;
+    // Writing is timed out too, it blocks while the hook doesn't read a full pipe
+    let status = future::or(
+        async {
+            if let Some(mut stdin) = stdin {
+                // The hook is free to not read its stdin, it's closed once written
+                let _ = stdin.write_all(&input).await;
+            }
+
+            Some(child.status().await)
+        },
+        async {
+            Timer::after(timeout).await;
+            None
+        }
+    )
+    .await;
+
+    let Some(status) = status else {
+        child.kill()?;
+        return Err(io::Error::other(format!("timed out after {timeout:?}")).into());
+    };
+
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with {status}")).into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Keeps a single instance running, so that there's one overlay and one writer of the stats
+use std::{
+    fs::{self, File, TryLockError},
+    path::PathBuf
+};
+
+use yanet::{Result, ResultExt};
+use zbus::Connection;
+
+use crate::dbus::TimerProxy;
+
+/// Held for the whole lifetime of the instance, the lock is released by the OS even on crash
+pub struct InstanceLock {
+    _file: File
+}
+
+impl InstanceLock {
+    #[cfg(not(debug_assertions))]
+    fn path() -> Result<PathBuf> {
+        let mut path = dirs::runtime_dir()
+            .or_else(dirs::data_dir)
+            .ok_or_eyre("Cannot determine runtime dir")?;
+        path.push("annoyodoro");
+        fs::create_dir_all(&path)?;
+        path.push("annoyodoro.lock");
+        Ok(path)
+    }
+
+    #[cfg(debug_assertions)]
+    fn path() -> Result<PathBuf> {
+        let mut path = PathBuf::from_iter(["testing-files"]);
+        fs::create_dir_all(&path)?;
+        path.push("annoyodoro.lock");
+        Ok(path)
+    }
+
+    /// Returns `None` if another instance holds the lock
+    pub fn acquire() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        let file = File::create(&path)
+            .wrap_err_with(|_| format!("Cannot open {}", path.to_string_lossy()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err.into())
+        }
+    }
+}
+
+/// Asks the running instance to open its main window or focus it
+pub fn raise_running() -> Result<()> {
+    smol::block_on(async {
+        let connection = Connection::session().await?;
+        TimerProxy::new(&connection).await?.show_window().await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,498 @@
+//! Keyboard shortcuts shared by the main window, the break overlay and the terminal UI
+use std::fmt::{self, Display};
+
+use iced::keyboard::{self, Key, Modifiers, key::Named};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Deserializer, de::Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Pause,
+    LastSession,
+    BreakNow,
+    OpenStats,
+    Quit,
+    FocusGoal,
+    FocusNext,
+    FocusPrevious,
+    NextSuggestion,
+    SuggestionDone,
+    ToggleBreathing,
+    DismissMicroBreak
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Pause => "pause",
+            Action::LastSession => "last_session",
+            Action::BreakNow => "break_now",
+            Action::OpenStats => "open_stats",
+            Action::Quit => "quit",
+            Action::FocusGoal => "focus_goal",
+            Action::FocusNext => "focus_next",
+            Action::FocusPrevious => "focus_previous",
+            Action::NextSuggestion => "next_suggestion",
+            Action::SuggestionDone => "suggestion_done",
+            Action::ToggleBreathing => "toggle_breathing",
+            Action::DismissMicroBreak => "dismiss_micro_break"
+        })
+    }
+}
+
+impl Action {
+    /// Whether the action is listed in the terminal UI, which has no break overlay and only one
+    /// text input
+    pub fn in_terminal(self) -> bool {
+        matches!(
+            self,
+            Action::Pause
+                | Action::LastSession
+                | Action::BreakNow
+                | Action::OpenStats
+                | Action::Quit
+                | Action::FocusGoal
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    modifiers: Modifiers,
+    key: ChordKey
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ChordKey {
+    Character(char),
+    Named(Named)
+}
+
+const NAMED_KEYS: [(&str, Named); 24] = [
+    ("space", Named::Space),
+    ("enter", Named::Enter),
+    ("escape", Named::Escape),
+    ("tab", Named::Tab),
+    ("backspace", Named::Backspace),
+    ("delete", Named::Delete),
+    ("up", Named::ArrowUp),
+    ("down", Named::ArrowDown),
+    ("left", Named::ArrowLeft),
+    ("right", Named::ArrowRight),
+    ("home", Named::Home),
+    ("end", Named::End),
+    ("f1", Named::F1),
+    ("f2", Named::F2),
+    ("f3", Named::F3),
+    ("f4", Named::F4),
+    ("f5", Named::F5),
+    ("f6", Named::F6),
+    ("f7", Named::F7),
+    ("f8", Named::F8),
+    ("f9", Named::F9),
+    ("f10", Named::F10),
+    ("f11", Named::F11),
+    ("f12", Named::F12)
+];
+
+impl Chord {
+    fn parse(chord: &str) -> Result<Self, String> {
+        let mut modifiers = Modifiers::empty();
+        let mut parts = chord.split('+').map(str::trim).peekable();
+        while let Some(part) = parts.next() {
+            let part = part.to_lowercase();
+            if parts.peek().is_some() {
+                modifiers |= match part.as_str() {
+                    "ctrl" | "control" => Modifiers::CTRL,
+                    "alt" => Modifiers::ALT,
+                    "shift" => Modifiers::SHIFT,
+                    "super" | "logo" => Modifiers::LOGO,
+                    _ => return Err(format!("Unknown modifier `{part}` in `{chord}`"))
+                };
+                continue;
+            }
+
+            let mut chars = part.chars();
+            let key = match (chars.next(), chars.next()) {
+                (Some(char), None) => ChordKey::Character(char),
+                _ => NAMED_KEYS
+                    .iter()
+                    .find(|(name, _)| *name == part)
+                    .map(|(_, named)| ChordKey::Named(*named))
+                    .ok_or_else(|| format!("Unknown key `{part}` in `{chord}`"))?
+            };
+
+            return Ok(Self { modifiers, key });
+        }
+
+        Err("Empty keybinding".to_string())
+    }
+
+    fn matches(
+        &self,
+        key: &Key,
+        physical_key: keyboard::key::Physical,
+        modifiers: Modifiers
+    ) -> bool {
+        if self.modifiers != modifiers {
+            return false;
+        }
+
+        match (self.key, key) {
+            (ChordKey::Named(named), Key::Named(pressed)) => named == *pressed,
+            (ChordKey::Character(char), Key::Character(_)) => key
+                .to_latin(physical_key)
+                .is_some_and(|pressed| pressed.to_ascii_lowercase() == char),
+            _ => false
+        }
+    }
+}
+
+impl Chord {
+    fn matches_terminal(&self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let modifiers = [
+            (KeyModifiers::CONTROL, Modifiers::CTRL),
+            (KeyModifiers::ALT, Modifiers::ALT),
+            (KeyModifiers::SHIFT, Modifiers::SHIFT),
+            (KeyModifiers::SUPER, Modifiers::LOGO)
+        ]
+        .into_iter()
+        .filter(|(terminal, _)| modifiers.contains(*terminal))
+        .fold(Modifiers::empty(), |acc, (_, modifier)| acc | modifier);
+        if self.modifiers != modifiers {
+            return false;
+        }
+
+        match (self.key, terminal_named_key(code), code) {
+            (ChordKey::Named(named), Some(pressed), _) => named == pressed,
+            (ChordKey::Character(char), None, KeyCode::Char(pressed)) => {
+                pressed.to_ascii_lowercase() == char
+            }
+            _ => false
+        }
+    }
+
+    /// Whether the chord would be typed into a text input rather than trigger an action
+    fn is_typed(&self) -> bool {
+        matches!(self.key, ChordKey::Character(_))
+            && !self
+                .modifiers
+                .intersects(Modifiers::CTRL | Modifiers::ALT | Modifiers::LOGO)
+    }
+}
+
+fn terminal_named_key(code: KeyCode) -> Option<Named> {
+    Some(match code {
+        KeyCode::Char(' ') => Named::Space,
+        KeyCode::Enter => Named::Enter,
+        KeyCode::Esc => Named::Escape,
+        KeyCode::Tab => Named::Tab,
+        KeyCode::Backspace => Named::Backspace,
+        KeyCode::Delete => Named::Delete,
+        KeyCode::Up => Named::ArrowUp,
+        KeyCode::Down => Named::ArrowDown,
+        KeyCode::Left => Named::ArrowLeft,
+        KeyCode::Right => Named::ArrowRight,
+        KeyCode::Home => Named::Home,
+        KeyCode::End => Named::End,
+        KeyCode::F(1) => Named::F1,
+        KeyCode::F(2) => Named::F2,
+        KeyCode::F(3) => Named::F3,
+        KeyCode::F(4) => Named::F4,
+        KeyCode::F(5) => Named::F5,
+        KeyCode::F(6) => Named::F6,
+        KeyCode::F(7) => Named::F7,
+        KeyCode::F(8) => Named::F8,
+        KeyCode::F(9) => Named::F9,
+        KeyCode::F(10) => Named::F10,
+        KeyCode::F(11) => Named::F11,
+        KeyCode::F(12) => Named::F12,
+        _ => return None
+    })
+}
+
+impl Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (Modifiers::CTRL, "ctrl"),
+            (Modifiers::ALT, "alt"),
+            (Modifiers::SHIFT, "shift"),
+            (Modifiers::LOGO, "super")
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        match self.key {
+            ChordKey::Character(char) => write!(f, "{char}"),
+            ChordKey::Named(named) => {
+                let (name, _) = NAMED_KEYS
+                    .iter()
+                    .find(|(_, key)| *key == named)
+                    .expect("Chords are only parsed from known keys");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// An empty string unbinds the action
+fn deserialize_chord<'de, D>(deserializer: D) -> Result<Option<Chord>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let chord = String::deserialize(deserializer)?;
+    if chord.trim().is_empty() {
+        return Ok(None);
+    }
+
+    Chord::parse(&chord).map(Some).map_err(Error::custom)
+}
+
+#[derive(Clone, Copy, Hash, Deserialize)]
+#[serde(try_from = "RawKeybindings")]
+pub struct Keybindings {
+    bindings: [(Action, Option<Chord>); 12]
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawKeybindings {
+    #[serde(deserialize_with = "deserialize_chord")]
+    pause: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    last_session: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    break_now: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    open_stats: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    quit: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    focus_goal: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    focus_next: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    focus_previous: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    next_suggestion: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    suggestion_done: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    toggle_breathing: Option<Chord>,
+    #[serde(deserialize_with = "deserialize_chord")]
+    dismiss_micro_break: Option<Chord>
+}
+
+impl TryFrom<RawKeybindings> for Keybindings {
+    type Error = String;
+
+    fn try_from(raw: RawKeybindings) -> Result<Self, Self::Error> {
+        let bindings = [
+            (Action::Pause, raw.pause),
+            (Action::LastSession, raw.last_session),
+            (Action::BreakNow, raw.break_now),
+            (Action::OpenStats, raw.open_stats),
+            (Action::Quit, raw.quit),
+            (Action::FocusGoal, raw.focus_goal),
+            (Action::FocusNext, raw.focus_next),
+            (Action::FocusPrevious, raw.focus_previous),
+            (Action::NextSuggestion, raw.next_suggestion),
+            (Action::SuggestionDone, raw.suggestion_done),
+            (Action::ToggleBreathing, raw.toggle_breathing),
+            (Action::DismissMicroBreak, raw.dismiss_micro_break)
+        ];
+
+        for (i, (action, chord)) in bindings.iter().enumerate() {
+            let Some(chord) = chord else { continue };
+            if let Some((other, _)) = bindings[i + 1..]
+                .iter()
+                .find(|(_, other)| *other == Some(*chord))
+            {
+                return Err(format!("`{chord}` is bound to both {action} and {other}"));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+}
+
+impl Keybindings {
+    #[cfg(test)]
+    pub fn unbound() -> Self {
+        Self {
+            bindings: [
+                Action::Pause,
+                Action::LastSession,
+                Action::BreakNow,
+                Action::OpenStats,
+                Action::Quit,
+                Action::FocusGoal,
+                Action::FocusNext,
+                Action::FocusPrevious,
+                Action::NextSuggestion,
+                Action::SuggestionDone,
+                Action::ToggleBreathing,
+                Action::DismissMicroBreak
+            ]
+            .map(|action| (action, None))
+        }
+    }
+
+    pub fn action(&self, event: keyboard::Event) -> Option<Action> {
+        let keyboard::Event::KeyPressed {
+            key,
+            physical_key,
+            modifiers,
+            repeat: false,
+            ..
+        } = event
+        else {
+            return None;
+        };
+
+        self.bindings
+            .iter()
+            .find(|(_, chord)| {
+                chord.is_some_and(|chord| chord.matches(&key, physical_key, modifiers))
+            })
+            .map(|(action, _)| *action)
+    }
+
+    /// Like [`Keybindings::action`] for the terminal UI. While `typing` the chords that would be
+    /// typed are ignored, like the main window ignores them when the text input is focused
+    pub fn terminal_action(&self, event: KeyEvent, typing: bool) -> Option<Action> {
+        if event.kind != KeyEventKind::Press {
+            return None;
+        }
+
+        self.bindings
+            .iter()
+            .find(|(_, chord)| {
+                chord.is_some_and(|chord| {
+                    !(typing && chord.is_typed())
+                        && chord.matches_terminal(event.code, event.modifiers)
+                })
+            })
+            .map(|(action, _)| *action)
+    }
+
+    pub fn chord(&self, action: Action) -> Option<Chord> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, chord)| *chord)
+    }
+
+    /// `label` followed by the chord bound to `action` if any, so that it's known without a mouse
+    pub fn label(&self, label: &str, action: Action) -> String {
+        match self.chord(action) {
+            Some(chord) => format!("{label} ({chord})"),
+            None => label.to_string()
+        }
+    }
+
+    pub fn bound(&self) -> impl Iterator<Item = (Action, Chord)> {
+        self.bindings
+            .iter()
+            .filter_map(|(action, chord)| chord.map(|chord| (*action, chord)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, Value, json};
+
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_and_keys() {
+        assert_eq!(
+            Chord::parse("ctrl+shift+s"),
+            Ok(Chord {
+                modifiers: Modifiers::CTRL | Modifiers::SHIFT,
+                key: ChordKey::Character('s')
+            })
+        );
+        assert_eq!(
+            Chord::parse("super + F5"),
+            Ok(Chord {
+                modifiers: Modifiers::LOGO,
+                key: ChordKey::Named(Named::F5)
+            })
+        );
+        assert_eq!(
+            Chord::parse("space"),
+            Ok(Chord {
+                modifiers: Modifiers::empty(),
+                key: ChordKey::Named(Named::Space)
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(Chord::parse("Control+Q"), Chord::parse("ctrl+q"));
+        assert_eq!(Chord::parse("ESCAPE"), Chord::parse("escape"));
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers_and_keys() {
+        assert!(Chord::parse("hyper+q").is_err());
+        assert!(Chord::parse("ctrl+pageup").is_err());
+        assert!(Chord::parse("ctrl+").is_err());
+        assert!(Chord::parse("").is_err());
+    }
+
+    #[test]
+    fn displays_like_it_parses() {
+        for chord in ["ctrl+alt+shift+super+x", "shift+tab", "f12", "p"] {
+            assert_eq!(Chord::parse(chord).unwrap().to_string(), chord);
+        }
+    }
+
+    fn keybindings(bound: &[(&str, &str)]) -> Result<Keybindings, serde_json::Error> {
+        let mut raw = Keybindings::unbound()
+            .bindings
+            .map(|(action, _)| (action.to_string(), json!("")))
+            .into_iter()
+            .collect::<Map<_, _>>();
+        for (action, chord) in bound {
+            raw.insert(action.to_string(), json!(chord));
+        }
+
+        serde_json::from_value(Value::Object(raw))
+    }
+
+    #[test]
+    fn rejects_conflicting_bindings() {
+        let err = keybindings(&[("pause", "ctrl+p"), ("quit", "Ctrl+P")])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("bound to both pause and quit"));
+        assert!(keybindings(&[("pause", "ctrl+p"), ("quit", "ctrl+q")]).is_ok());
+    }
+
+    #[test]
+    fn labels_show_the_bound_chord() {
+        let keybindings = keybindings(&[("pause", "space"), ("break_now", "ctrl+b")]).unwrap();
+        assert_eq!(
+            keybindings.chord(Action::BreakNow),
+            Chord::parse("ctrl+b").ok()
+        );
+        assert_eq!(
+            keybindings.label("Break now", Action::BreakNow),
+            "Break now (ctrl+b)"
+        );
+        assert_eq!(keybindings.label("Pause", Action::Pause), "Pause (space)");
+        assert_eq!(keybindings.label("Quit", Action::Quit), "Quit");
+    }
+
+    #[test]
+    fn empty_unbinds() {
+        let keybindings = keybindings(&[("pause", "p")]).unwrap();
+        assert_eq!(keybindings.bound().count(), 1);
+        assert_eq!(keybindings.chord(Action::Quit), None);
+    }
+}
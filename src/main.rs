@@ -1,31 +1,67 @@
 #![cfg_attr(not(debug_assertions), allow(unused_imports))]
 
+mod announcements;
 mod break_timer;
+mod breathing;
 mod circular;
 mod cli;
+mod clock;
+mod color_scheme;
 mod config;
+mod dbus;
+mod enforcement;
+mod hooks;
+mod instance;
+mod keybindings;
+mod media;
+mod micro_breaks;
+mod notifications;
+mod session;
+mod sounds;
 mod stats;
+mod suggestions;
+mod themes;
+mod tray;
+mod tui;
+mod ui;
 mod view;
 mod work_timer;
 
-use std::{cell::RefCell, mem};
+use std::{cell::RefCell, io, mem, time::Duration};
 
-use break_timer::BreakTimer;
+use announcements::Announcer;
+use break_timer::{BreakMessage, BreakSummary, BreakTimer};
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
+use clock::SystemClock;
+use color_scheme::ColorScheme;
 use config::Config;
+use dbus::{DbusCommand, DbusEvent, DbusHandle, Phase, TimerState};
+use enforcement::Enforcement;
+use hooks::{HookData, HookEvent};
 use iced::{
-    Event, Subscription, Task,
-    event::Status,
-    exit,
-    keyboard::{self, Key},
-    widget::operation::focus,
-    window::{self, Id}
+    Color, Size, Subscription, Task, event, exit, keyboard, theme, time,
+    widget::operation::{focus, focus_next, focus_previous},
+    window
 };
+use iced_layershell::{
+    reexport::{Anchor, KeyboardInteractivity, Layer, NewLayerShellSettings, NewXdgWindowSettings},
+    settings::{LayerShellSettings, Settings, StartMode},
+    to_layer_message
+};
+use instance::InstanceLock;
+use keybindings::Action;
 use lucide_icons::LUCIDE_FONT_BYTES;
+use media::Media;
+use micro_breaks::{MicroBreakChange, MicroBreaks};
+use notifications::{NotificationAction, PreBreakNotification};
 use notify_rust::Notification;
+use session::{Effect, Session, SessionEvent, SessionPhase};
+use smol::future;
+use sounds::{Cue, Sounds};
 use stats::StatsManager;
-use work_timer::WorkTimer;
+use suggestions::Suggestions;
+use tray::{ICON_STEPS, TrayAction, TrayEvent, TrayHandle, TrayState};
 use yanet::Result;
 
 fn main() -> Result<()> {
@@ -40,19 +76,98 @@ fn main() -> Result<()> {
         return Config::write_default();
     };
 
-    let config = Config::new()?;
+    let changes_config = cli.config.is_some() || !cli.overrides.is_empty();
+    let config = Config::new(cli.config, &cli.overrides)?;
+    if let Some(Command::Ui) = cli.command {
+        return ui::run(config);
+    }
+
+    let lock = match InstanceLock::acquire()? {
+        Some(lock) => Some(lock),
+        None if cli.force_new => {
+            eprintln!(
+                "annoyodoro is already running, the D-Bus interface is left to it and this \
+                 instance can't be raised or controlled over D-Bus"
+            );
+            None
+        }
+        None if cli.command.is_some() => {
+            return Err(io::Error::other("annoyodoro is already running").into());
+        }
+        None => {
+            if changes_config {
+                eprintln!("annoyodoro is already running, its config stays unchanged");
+            }
+
+            return instance::raise_running();
+        }
+    };
+    // The bus name is owned by the running instance
+    let serves_dbus = lock.is_some();
+
+    let headless = match cli.command {
+        Some(Command::Tui) => return tui::run(config),
+        Some(Command::Daemon) => true,
+        Some(Command::Ui) | None => false
+    };
+
     let default_font = config.font;
-    let theme = config.theme();
     let stats = StatsManager::load()?;
-    let once_boot = RefCell::new(Some(Annoyodoro::new(config, stats)));
-    let boot = move || (once_boot.borrow_mut().take().unwrap(), focus("work-goal"));
+    let sounds = Sounds::spawn(config.sounds);
+    let media = Media::spawn(config.media);
+    let suggestions = Suggestions::load(config.suggestions)?;
+    let enforcement = Enforcement::detect();
+    let once_boot = RefCell::new(Some(Annoyodoro {
+        serves_dbus,
+        ..Annoyodoro::new(
+            config,
+            stats,
+            sounds,
+            media,
+            suggestions,
+            enforcement,
+            headless
+        )
+    }));
+    let boot = move || {
+        let mut annoyodoro = once_boot.borrow_mut().take().unwrap();
+        let open = if headless {
+            Task::none()
+        } else {
+            annoyodoro.show_window()
+        };
+        (annoyodoro, open)
+    };
 
-    iced::application(boot, Annoyodoro::update, Annoyodoro::view)
-        .subscription(Annoyodoro::subscription)
-        .default_font(default_font)
-        .font(LUCIDE_FONT_BYTES)
-        .theme(move |_: &Annoyodoro| theme.clone())
-        .run()?;
+    // A daemon so that closing the main window doesn't stop the timer while there's a tray icon
+    match enforcement {
+        // The break overlay is a layer-shell surface, so the whole app runs on iced_layershell
+        Enforcement::LayerShell => {
+            iced_layershell::daemon(boot, "annoyodoro", Annoyodoro::update, Annoyodoro::view)
+                .settings(Settings {
+                    layer_settings: LayerShellSettings {
+                        // No surface until the main window or the break overlay is opened
+                        start_mode: StartMode::Background,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .subscription(Annoyodoro::subscription)
+                .default_font(default_font)
+                .font(LUCIDE_FONT_BYTES)
+                .theme(|annoyodoro: &Annoyodoro, _| annoyodoro.config.theme())
+                .style(Annoyodoro::style)
+                .run()?
+        }
+        Enforcement::Window => iced::daemon(boot, Annoyodoro::update, Annoyodoro::view)
+            .title("Annoyodoro")
+            .subscription(Annoyodoro::subscription)
+            .default_font(default_font)
+            .font(LUCIDE_FONT_BYTES)
+            .theme(|annoyodoro: &Annoyodoro, _| annoyodoro.config.theme())
+            .style(Annoyodoro::style)
+            .run()?
+    }
 
     Ok(())
 }
@@ -60,23 +175,74 @@ fn main() -> Result<()> {
 struct Annoyodoro {
     config: Config,
     stats: StatsManager,
-    state: AppState,
+    sounds: Sounds,
+    media: Media,
+    suggestions: Suggestions,
+    session: Session,
+    /// The goal being typed in the prompt for the first work session
+    goal: String,
+    showing_stats: bool,
+    /// `None` while the main window is closed
+    window: Option<window::Id>,
+    /// Started by `annoyodoro daemon`, the main window is only opened from the tray
+    headless: bool,
+    /// Unless started with `--force-new` while another instance is running
+    serves_dbus: bool,
+    /// `None` until the tray icon is registered, or if there's no StatusNotifierItem host
+    tray: Option<TrayHandle>,
+    /// `None` until the D-Bus interface is served
+    dbus: Option<DbusHandle>,
+    enforcement: Enforcement,
+    /// `None` outside of breaks
+    on_break: Option<BreakSurface>,
+    micro_breaks: MicroBreaks,
+    /// `None` outside of micro-breaks, or if they're shown as notifications
+    micro_break_window: Option<window::Id>,
+    /// Size of the output the break overlay was last shown on, `None` until it's known
+    output_size: Option<Size>,
+    announcer: Announcer,
+    pre_break_notification: PreBreakNotification,
     error: Option<String>
 }
 
-#[derive(Debug)]
-enum AppState {
-    InitialWorkGoalPrompt {
-        goal: String
-    },
-    Running {
-        long_break_in: u16,
-        work_timer: WorkTimer,
-        last_work_session: bool,
-        shown_notification: bool
-    }
+/// Opens the surface the break timer is shown on
+fn open_break_surface(enforcement: Enforcement) -> (window::Id, Task<Message>) {
+    let (window, open) = match enforcement {
+        Enforcement::LayerShell => {
+            let id = window::Id::unique();
+            let settings = NewLayerShellSettings {
+                anchor: Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right,
+                layer: Layer::Overlay,
+                // Covers panels too
+                exclusive_zone: Some(-1),
+                keyboard_interactivity: KeyboardInteractivity::Exclusive,
+                ..Default::default()
+            };
+            (id, Task::done(Message::NewLayerShell { settings, id }))
+        }
+        Enforcement::Window => {
+            let (id, open) = window::open(window::Settings {
+                fullscreen: true,
+                level: window::Level::AlwaysOnTop,
+                // For `layout.overlay_opacity`
+                transparent: true,
+                decorations: false,
+                exit_on_close_request: false,
+                ..Default::default()
+            });
+            (id, open.discard())
+        }
+    };
+
+    (window, open.chain(focus("work-goal")))
 }
 
+struct BreakSurface {
+    window: window::Id,
+    timer: BreakTimer
+}
+
+#[to_layer_message(multi)]
 #[derive(Debug, Clone)]
 enum Message {
     InitialWorkGoalChange(String),
@@ -84,164 +250,651 @@ enum Message {
 
     TogglePause,
     ToggleLastWorkSession,
-    Tick,
+    BreakNow,
+    ToggleStats,
+    FocusGoal,
+    FocusNext,
+    FocusPrevious,
+    Quit,
+    /// The number of times the notification was shown when the action was invoked
+    NotificationAction(u64, NotificationAction),
+    Tray(TrayEvent),
+    Dbus(DbusEvent),
+    Break(BreakMessage),
+    DismissMicroBreak,
+    WindowClosed(window::Id),
+    WindowResized(window::Id, Size),
+    ColorScheme(ColorScheme),
+    Tick
+}
 
-    #[cfg(debug_assertions)]
-    DebugEarlyBreak
+impl From<Action> for Message {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Pause => Message::TogglePause,
+            Action::LastSession => Message::ToggleLastWorkSession,
+            Action::BreakNow => Message::BreakNow,
+            Action::OpenStats => Message::ToggleStats,
+            Action::Quit => Message::Quit,
+            Action::FocusGoal => Message::FocusGoal,
+            Action::FocusNext => Message::FocusNext,
+            Action::FocusPrevious => Message::FocusPrevious,
+            Action::NextSuggestion => Message::Break(BreakMessage::NextSuggestion),
+            Action::SuggestionDone => Message::Break(BreakMessage::SuggestionDone),
+            Action::ToggleBreathing => Message::Break(BreakMessage::ToggleBreathing),
+            Action::DismissMicroBreak => Message::DismissMicroBreak
+        }
+    }
 }
 
 impl Annoyodoro {
-    fn new(config: Config, stats: StatsManager) -> Self {
+    fn new(
+        config: Config,
+        stats: StatsManager,
+        sounds: Sounds,
+        media: Media,
+        suggestions: Suggestions,
+        enforcement: Enforcement,
+        headless: bool
+    ) -> Self {
         Annoyodoro {
             config,
             stats,
-            state: AppState::InitialWorkGoalPrompt {
-                goal: String::new()
-            },
+            sounds,
+            media,
+            suggestions,
+            session: Session::new(config.pomodoro),
+            goal: String::new(),
+            showing_stats: false,
+            window: None,
+            headless,
+            serves_dbus: true,
+            tray: None,
+            dbus: None,
+            enforcement,
+            on_break: None,
+            micro_breaks: MicroBreaks::new(config.micro_breaks, &SystemClock),
+            micro_break_window: None,
+            output_size: None,
+            announcer: Announcer::new(config.accessibility.announce_interval),
+            pre_break_notification: PreBreakNotification::default(),
             error: None
         }
     }
 
-    fn break_time(&mut self, long_break: bool) -> Result<()> {
-        let goal = BreakTimer::spawn(long_break, self.config)?;
-        if let AppState::Running { work_timer, .. } = &mut self.state {
-            *work_timer = WorkTimer::new(self.config.pomodoro.work_duration);
-            self.stats.increment_pomodori_count();
-            self.stats.add_work_goal(goal);
-            self.stats.save()?;
-            self.stats.reload_if_needed()?;
+    fn run_hook(&self, event: HookEvent, long_break: Option<bool>) {
+        self.config.hooks.run(self.hook_data(event, long_break));
+    }
+
+    fn hook_data(&self, event: HookEvent, long_break: Option<bool>) -> HookData {
+        let pomodoro = self.config.pomodoro;
+        let work_remaining = match self.session.phase() {
+            SessionPhase::Work { work_timer, .. } => work_timer.duration_remaning(),
+            SessionPhase::Break { .. } => Duration::ZERO,
+            SessionPhase::WaitingForGoal => pomodoro.work_duration
+        };
+
+        HookData {
+            event,
+            goal: self
+                .stats
+                .work_goals()
+                .last()
+                .map(|(_, goal)| goal.clone())
+                .unwrap_or_default(),
+            work_duration_secs: pomodoro.work_duration.as_secs(),
+            work_remaining_secs: work_remaining.as_secs(),
+            long_break_in: self.session.long_break_in(),
+            pomodori_today: self.stats.pomodori_daily(),
+            long_break,
+            break_duration_secs: long_break
+                .map(|long_break| pomodoro.break_duration(long_break).as_secs())
         }
+    }
+
+    /// Feeds `event` to the session and carries out what it results in
+    fn session_event(&mut self, event: SessionEvent) -> Result<Task<Message>> {
+        let effects = self.session.handle(event, &SystemClock);
+        // Run side by side, e.g. a break mustn't wait for the notification to be acted on
+        let mut tasks = effects
+            .into_iter()
+            .map(|effect| self.run_effect(effect))
+            .collect::<Result<Vec<_>>>()?;
 
-        Ok(())
+        let working = self
+            .session
+            .work_timer()
+            .is_some_and(|work_timer| !work_timer.is_paused());
+        let change = self.micro_breaks.tick(working, &SystemClock);
+        tasks.push(self.micro_break_change(change)?);
+        Ok(Task::batch(tasks))
     }
 
-    fn update(&mut self, message: Message) -> Task<Message> {
-        self.try_update(message).unwrap_or_else(|err| {
-            dbg!(&err);
-            self.error = Some(err.to_string());
-            Task::none()
-        })
+    /// Shows or hides the micro-break overlay
+    fn micro_break_change(&mut self, change: Option<MicroBreakChange>) -> Result<Task<Message>> {
+        match change {
+            Some(MicroBreakChange::Start) => match self.enforcement {
+                Enforcement::LayerShell => {
+                    let id = window::Id::unique();
+                    let settings = NewLayerShellSettings {
+                        size: Some((400, 120)),
+                        anchor: Anchor::Top,
+                        layer: Layer::Overlay,
+                        margin: Some((40, 0, 0, 0)),
+                        // Only takes the keyboard when clicked, not from whatever is being typed into
+                        keyboard_interactivity: KeyboardInteractivity::OnDemand,
+                        ..Default::default()
+                    };
+                    self.micro_break_window = Some(id);
+                    Ok(Task::done(Message::NewLayerShell { settings, id }))
+                }
+                Enforcement::Window => {
+                    notifications::show_micro_break(self.config.micro_breaks.duration)?;
+                    Ok(Task::none())
+                }
+            },
+            Some(MicroBreakChange::End) => Ok(self
+                .micro_break_window
+                .take()
+                .map_or_else(Task::none, |id| self.close_surface(id))),
+            None => Ok(Task::none())
+        }
     }
 
-    fn try_update(&mut self, message: Message) -> Result<Task<Message>> {
-        match (message, &mut self.state) {
-            (
-                Message::Tick,
-                AppState::Running {
-                    long_break_in,
-                    work_timer,
-                    last_work_session,
-                    shown_notification
+    fn run_effect(&mut self, effect: Effect) -> Result<Task<Message>> {
+        match effect {
+            Effect::PreBreakNotification { remaining } => {
+                let action = self
+                    .pre_break_notification
+                    .show(remaining, self.config.pomodoro.snooze_duration)?;
+                self.run_hook(HookEvent::PreBreakNotification, None);
+                self.sounds.play(Cue::PreBreak);
+                return Ok(action.map(|(shown, action)| Message::NotificationAction(shown, action)));
+            }
+            Effect::Paused => self.run_hook(HookEvent::Pause, None),
+            Effect::Resumed => self.run_hook(HookEvent::Resume, None),
+            Effect::BreakStart { long_break } => {
+                // Its actions are meant for work
+                let close = self.pre_break_notification.close();
+                return Ok(Task::batch([close, self.break_time(long_break)?]));
+            }
+            Effect::BreakEnd { long_break } => {
+                self.run_hook(HookEvent::BreakEnd, Some(long_break));
+                if let Some(BreakSurface { window, timer }) = self.on_break.take() {
+                    self.media.resume();
+                    if timer.suggestion_done() {
+                        self.stats.increment_suggestions_done_count();
+                        self.stats.save()?;
+                    }
+
+                    return Ok(self.close_surface(window));
                 }
-            ) => {
-                work_timer.on_tick();
-                let duration_remaning = work_timer.duration_remaning();
-                if duration_remaning <= self.config.pomodoro.notification_duration
-                    && !*shown_notification
-                {
-                    *shown_notification = true;
-                    let body = format!(
-                        "Next break in {}:{:02}",
-                        duration_remaning.as_secs() / 60,
-                        duration_remaning.as_secs() % 60
-                    );
-                    Notification::new()
-                        .summary("Annoyodoro")
-                        .body(body.as_str())
-                        .show()?;
+            }
+            Effect::WorkStart { goal, first } => {
+                if first {
+                    self.stats.increment_app_sessions_count();
+                } else {
+                    self.stats.increment_pomodori_count();
                 }
 
-                if !work_timer.duration_remaning().is_zero() {
+                self.stats.add_work_goal(goal);
+                self.stats.save()?;
+                self.stats.reload_if_needed()?;
+                self.run_hook(HookEvent::GoalSubmitted, None);
+                self.run_hook(HookEvent::WorkStart, None);
+            }
+            Effect::LastWorkSessionOver => {
+                Notification::new()
+                    .summary("Annoyodoro")
+                    .body("Last work session is over! Exiting annoyodoro")
+                    .show()?;
+            }
+            Effect::SessionEnd => {
+                // The process would end before the cue is heard and the hook is run
+                let cue = self.sounds.play_until_end(Cue::SessionEnd);
+                let hook = self
+                    .config
+                    .hooks
+                    .run_until_done(self.hook_data(HookEvent::SessionEnd, None));
+                return Ok(Task::future(future::zip(cue, hook)).then(|_| exit()));
+            }
+        }
+
+        Ok(Task::none())
+    }
+
+    fn break_time(&mut self, long_break: bool) -> Result<Task<Message>> {
+        self.run_hook(HookEvent::BreakStart, Some(long_break));
+        self.sounds.play(Cue::BreakStart);
+        let change = self.micro_breaks.restart(&SystemClock);
+        let close_micro_break = self.micro_break_change(change)?;
+        // The work session may have crossed midnight
+        self.stats.reload_if_needed()?;
+        let (window, open) = open_break_surface(self.enforcement);
+        self.on_break = Some(BreakSurface {
+            window,
+            timer: BreakTimer::new(
+                long_break,
+                self.config,
+                self.sounds.clone(),
+                self.suggestions.for_break(long_break),
+                self.break_summary(),
+                &SystemClock
+            )?
+        });
+        self.media.pause_playing();
+        Ok(close_micro_break.chain(open))
+    }
+
+    /// The work session before the break is only counted once the next one starts, so it's added
+    fn break_summary(&self) -> BreakSummary {
+        BreakSummary {
+            pomodori_today: self.stats.pomodori_daily() + 1,
+            daily_target: self.config.pomodoro.daily_target,
+            goals_today: self.stats.work_goals_today(),
+            long_break_in: self.session.long_break_in(),
+            streak: self.stats.streak_until_yesterday() + 1
+        }
+    }
+
+    /// Opens the main window or focuses it if it's already open
+    fn show_window(&mut self) -> Task<Message> {
+        if let Some(id) = self.window {
+            return window::gain_focus(id);
+        }
+
+        let (id, open) = match self.enforcement {
+            // iced_layershell opens windows through messages
+            Enforcement::LayerShell => {
+                let id = window::Id::unique();
+                let settings = NewXdgWindowSettings {
+                    title: Some("Annoyodoro".to_string()),
+                    size: None
+                };
+                (id, Task::done(Message::NewBaseWindow { settings, id }))
+            }
+            Enforcement::Window => {
+                let (id, open) = window::open(window::Settings::default());
+                (id, open.discard())
+            }
+        };
+
+        self.window = Some(id);
+        open.chain(focus("work-goal"))
+    }
+
+    fn close_surface(&self, id: window::Id) -> Task<Message> {
+        match self.enforcement {
+            Enforcement::LayerShell => Task::done(Message::RemoveWindow(id)),
+            Enforcement::Window => window::close(id)
+        }
+    }
+
+    fn tray_state(&self) -> TrayState {
+        let last_work_session = self.session.last_work_session();
+        let ring_colors = self.config.ring_colors();
+        let (accent, track) = (
+            ring_colors.work.into_rgba8(),
+            ring_colors.track.into_rgba8()
+        );
+        let work_timer = match self.session.phase() {
+            SessionPhase::Work { work_timer, .. } => work_timer,
+            SessionPhase::Break { .. } => {
+                let remaining = self
+                    .on_break
+                    .as_ref()
+                    .map_or(Duration::ZERO, |on_break| on_break.timer.remaining());
+                return TrayState {
+                    remaining_secs: Some(remaining.as_secs()),
+                    on_break: true,
+                    progress: ICON_STEPS,
+                    paused: false,
+                    last_work_session,
+                    accent,
+                    track
+                };
+            }
+            SessionPhase::WaitingForGoal => {
+                return TrayState {
+                    accent,
+                    track,
+                    ..TrayState::default()
+                };
+            }
+        };
+
+        let remaining = work_timer.duration_remaning();
+        let progress = (1.0
+            - remaining.as_millis() as f32 / self.config.pomodoro.work_duration.as_millis() as f32)
+            .max(0.0);
+        TrayState {
+            remaining_secs: Some(remaining.as_secs()),
+            on_break: false,
+            progress: (progress * f32::from(ICON_STEPS)).round() as u8,
+            paused: work_timer.is_paused(),
+            last_work_session,
+            accent,
+            track
+        }
+    }
+
+    fn timer_state(&self) -> TimerState {
+        let (phase, work_timer) = match self.session.phase() {
+            SessionPhase::WaitingForGoal => (Phase::WaitingForGoal, None),
+            SessionPhase::Work { work_timer, .. } => (Phase::Work, Some(work_timer)),
+            SessionPhase::Break { .. } => (Phase::Break, None)
+        };
+
+        TimerState {
+            phase,
+            remaining_secs: work_timer.map_or(0, |timer| timer.duration_remaning().as_secs()),
+            paused: work_timer.is_some_and(|timer| timer.is_paused()),
+            last_session: self.session.last_work_session(),
+            long_break_in: self.session.long_break_in(),
+            pomodori_today: self.stats.pomodori_daily(),
+            current_goal: self
+                .stats
+                .work_goals()
+                .last()
+                .filter(|_| phase != Phase::WaitingForGoal)
+                .map(|(_, goal)| goal.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.session
+            .work_timer()
+            .is_some_and(|work_timer| work_timer.is_paused())
+    }
+
+    fn dbus_command(&mut self, command: DbusCommand) -> Result<Task<Message>> {
+        match command {
+            DbusCommand::Pause if !self.is_paused() => {
+                self.session_event(SessionEvent::TogglePause)
+            }
+            DbusCommand::Resume if self.is_paused() => {
+                self.session_event(SessionEvent::TogglePause)
+            }
+            DbusCommand::Pause | DbusCommand::Resume => Ok(Task::none()),
+            DbusCommand::StartBreak => self.session_event(SessionEvent::BreakNow),
+            DbusCommand::ShowWindow => Ok(self.show_window()),
+            DbusCommand::SetLastSession(last_session) => {
+                self.session_event(SessionEvent::SetLastWorkSession(last_session))
+            }
+            DbusCommand::SubmitGoal(goal) => {
+                if !matches!(self.session.phase(), SessionPhase::WaitingForGoal) {
                     return Ok(Task::none());
                 }
 
-                if *last_work_session {
-                    Notification::new()
-                        .summary("Annoyodoro")
-                        .body("Last work session is over! Exiting annoyodoro")
-                        .show()?;
-                    return Ok(exit());
+                self.goal.clear();
+                self.session_event(SessionEvent::GoalSubmitted(goal))
+            }
+        }
+    }
+
+    fn tray_action(&mut self, action: TrayAction) -> Result<Task<Message>> {
+        match action {
+            TrayAction::ShowWindow => Ok(self.show_window()),
+            TrayAction::OpenStats => {
+                self.showing_stats = true;
+                Ok(self.show_window())
+            }
+            TrayAction::TogglePause => self.try_update(Message::TogglePause),
+            TrayAction::ToggleLastWorkSession => self.try_update(Message::ToggleLastWorkSession),
+            TrayAction::BreakNow => self.try_update(Message::BreakNow),
+            TrayAction::Quit => self.try_update(Message::Quit)
+        }
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        let task = self.try_update(message).unwrap_or_else(|err| {
+            dbg!(&err);
+            self.error = Some(err.to_string());
+            Task::none()
+        });
+
+        let tray_state = self.tray_state();
+        let timer_state = self.timer_state();
+        let task = match &mut self.tray {
+            Some(tray) => Task::batch([task, tray.update(tray_state)]),
+            None => task
+        };
+
+        if let Some(dbus) = &mut self.dbus {
+            dbus.update(timer_state);
+        }
+
+        task
+    }
+
+    fn try_update(&mut self, message: Message) -> Result<Task<Message>> {
+        match message {
+            Message::Tick => {
+                if let Some(on_break) = &mut self.on_break {
+                    on_break.timer.update(BreakMessage::Tick, &SystemClock);
                 }
 
-                *long_break_in -= 1;
-                let long_break = if *long_break_in == 0 {
-                    *long_break_in = self.config.pomodoro.long_break_each.into();
-                    true
-                } else {
-                    false
+                let task = self.session_event(SessionEvent::Tick)?;
+                self.announce();
+                return Ok(task);
+            }
+            Message::Break(message) => {
+                let goal = self
+                    .on_break
+                    .as_mut()
+                    .and_then(|on_break| on_break.timer.update(message, &SystemClock));
+                if let Some(goal) = goal {
+                    return self.session_event(SessionEvent::GoalSubmitted(goal));
+                }
+            }
+            Message::DismissMicroBreak => {
+                let change = self.micro_breaks.dismiss(&SystemClock);
+                return self.micro_break_change(change);
+            }
+            Message::TogglePause => return self.session_event(SessionEvent::TogglePause),
+            Message::ToggleLastWorkSession => {
+                return self.session_event(SessionEvent::ToggleLastWorkSession);
+            }
+            Message::BreakNow => return self.session_event(SessionEvent::BreakNow),
+            Message::NotificationAction(shown, _)
+                if !self.pre_break_notification.is_current(shown) => {}
+            Message::NotificationAction(_, action) => {
+                let event = match action {
+                    NotificationAction::BreakNow => SessionEvent::BreakNow,
+                    NotificationAction::Snooze => SessionEvent::Snooze,
+                    NotificationAction::Pause if !self.is_paused() => SessionEvent::TogglePause,
+                    NotificationAction::Pause => return Ok(Task::none())
                 };
+                return self.session_event(event);
+            }
+            Message::ToggleStats => self.showing_stats = !self.showing_stats,
+            Message::FocusGoal => return Ok(focus("work-goal")),
+            Message::FocusNext => return Ok(focus_next()),
+            Message::FocusPrevious => return Ok(focus_previous()),
+            Message::Tray(TrayEvent::Ready(tray)) => self.tray = Some(tray),
+            Message::Tray(TrayEvent::Action(action)) => return self.tray_action(action),
+            Message::Dbus(DbusEvent::Ready(dbus)) => self.dbus = Some(dbus),
+            Message::Dbus(DbusEvent::Command(command)) => return self.dbus_command(command),
+            Message::WindowClosed(id) if self.window == Some(id) => {
+                self.window = None;
+                if self.tray.is_none() && !self.headless {
+                    return self.session_event(SessionEvent::Quit);
+                }
+            }
+            Message::WindowClosed(id)
+                if self
+                    .on_break
+                    .as_ref()
+                    .is_some_and(|on_break| on_break.window == id) =>
+            {
+                // Closing the break window doesn't end the break
+                let (window, open) = open_break_surface(self.enforcement);
+                if let Some(on_break) = &mut self.on_break {
+                    on_break.window = window;
+                }
 
-                self.break_time(long_break)?;
+                return Ok(open);
             }
-            (Message::TogglePause, AppState::Running { work_timer, .. }) => {
-                work_timer.toggle_pause()
+            Message::WindowClosed(id) if self.micro_break_window == Some(id) => {
+                self.micro_break_window = None;
             }
-            #[cfg(debug_assertions)]
-            (Message::DebugEarlyBreak, AppState::Running { long_break_in, .. }) => {
-                *long_break_in -= 1;
-                let long_break = if *long_break_in == 0 {
-                    *long_break_in = self.config.pomodoro.long_break_each.into();
-                    true
-                } else {
-                    false
-                };
-                self.break_time(long_break)?;
-            }
-            #[cfg(debug_assertions)]
-            (Message::DebugEarlyBreak, _) => panic!(),
-            (
-                Message::InitialWorkGoalChange(goal),
-                AppState::InitialWorkGoalPrompt { goal: work_goal }
-            ) => *work_goal = goal,
-            (
-                Message::InitialWorkGoalSubmit,
-                AppState::InitialWorkGoalPrompt { goal: work_goal }
-            ) => {
-                self.stats.add_work_goal(mem::take(work_goal));
-                self.stats.increment_app_sessions_count();
-                self.state = AppState::Running {
-                    long_break_in: self.config.pomodoro.long_break_each.into(),
-                    work_timer: WorkTimer::new(self.config.pomodoro.work_duration),
-                    last_work_session: false,
-                    shown_notification: false
-                };
-                self.stats.save()?;
-                self.stats.reload_if_needed()?;
+            Message::WindowClosed(_) => {}
+            // The break surfaces cover the whole output
+            Message::WindowResized(id, size)
+                if self
+                    .on_break
+                    .as_ref()
+                    .is_some_and(|on_break| on_break.window == id) =>
+            {
+                self.output_size = Some(size);
+            }
+            Message::WindowResized(..) => {}
+            Message::ColorScheme(color_scheme) => {
+                self.config.set_color_scheme(color_scheme);
+                if let Some(on_break) = &mut self.on_break {
+                    on_break
+                        .timer
+                        .set_theme(self.config.theme(), self.config.ring_colors());
+                }
             }
-            (
-                Message::ToggleLastWorkSession,
-                AppState::Running {
-                    last_work_session, ..
+            Message::Quit => return self.session_event(SessionEvent::Quit),
+            Message::InitialWorkGoalChange(goal) => self.goal = goal,
+            Message::InitialWorkGoalSubmit => {
+                if matches!(self.session.phase(), SessionPhase::WaitingForGoal) {
+                    let goal = mem::take(&mut self.goal);
+                    return self.session_event(SessionEvent::GoalSubmitted(goal));
                 }
-            ) => *last_work_session = !*last_work_session,
-            (Message::InitialWorkGoalChange(_), AppState::Running { .. }) => {}
-            (Message::InitialWorkGoalSubmit, AppState::Running { .. }) => {}
-            (Message::TogglePause, AppState::InitialWorkGoalPrompt { .. }) => {}
-            (Message::ToggleLastWorkSession, AppState::InitialWorkGoalPrompt { .. }) => {}
-            (Message::Tick, AppState::InitialWorkGoalPrompt { .. }) => {}
+            }
+            // Taken by iced_layershell before they reach `update`
+            Message::AnchorChange { .. }
+            | Message::SetInputRegion { .. }
+            | Message::AnchorSizeChange { .. }
+            | Message::LayerChange { .. }
+            | Message::MarginChange { .. }
+            | Message::SizeChange { .. }
+            | Message::ExclusiveZoneChange { .. }
+            | Message::KeyboardInteractivityChange { .. }
+            | Message::VirtualKeyboardPressed { .. }
+            | Message::NewLayerShell { .. }
+            | Message::NewBaseWindow { .. }
+            | Message::NewPopUp { .. }
+            | Message::NewMenu { .. }
+            | Message::NewInputPanel { .. }
+            | Message::RemoveWindow(_)
+            | Message::ForgetLastOutput => {}
         }
 
         Ok(Task::none())
     }
 
-    fn key_subscription(event: Event, _: Status, _: Id) -> Option<Message> {
-        if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event
-            && modifiers.is_empty()
-        {
-            return match key {
-                Key::Character(char) if char == "p" => Some(Message::TogglePause),
-                Key::Character(char) if char == "l" => Some(Message::ToggleLastWorkSession),
-                _ => None
+    /// `None` while nothing counts down. Without a window only the whole seconds matter, for the
+    /// tray, the D-Bus interface and the micro-breaks
+    fn tick_interval(&self) -> Option<Duration> {
+        let reduced_motion = self.config.accessibility.reduced_motion;
+        if self.on_break.is_some() {
+            return Some(if reduced_motion {
+                Duration::from_secs(1)
+            } else {
+                self.config.ring_update_interval
+            });
+        }
+
+        let work = self
+            .session
+            .work_timer()
+            .filter(|work_timer| !work_timer.is_paused())
+            .map(|work_timer| {
+                if self.window.is_some() && !reduced_motion {
+                    self.config.ring_update_interval
+                } else {
+                    work_timer.until_next_second()
+                }
+            });
+        // A micro-break goes on while the work timer is paused
+        let micro_break = self
+            .micro_breaks
+            .current()
+            .map(|micro_break| micro_break.until_next_second());
+        work.into_iter().chain(micro_break).min()
+    }
+
+    /// Announces the remaining time of what's counting down, if announcements are enabled
+    fn announce(&mut self) {
+        let Some(command) = self.config.accessibility.announce_command else {
+            return;
+        };
+
+        let (countdown, remaining) = match (self.session.phase(), &self.on_break) {
+            (SessionPhase::Work { work_timer, .. }, _) if !work_timer.is_paused() => {
+                ("Work", work_timer.duration_remaning())
             }
+            (SessionPhase::Break { long_break }, Some(on_break)) => (
+                if long_break { "Long break" } else { "Break" },
+                on_break.timer.remaining()
+            ),
+            _ => return
+        };
+
+        if let Some(announcement) = self.announcer.announcement(countdown, remaining) {
+            announcements::announce(command, announcement);
         }
+    }
 
-        None
+    /// The surfaces draw their own backgrounds
+    fn style(&self, theme: &iced::Theme) -> theme::Style {
+        theme::Style {
+            background_color: Color::TRANSPARENT,
+            text_color: theme.palette().text
+        }
     }
 
     fn subscription(&self) -> Subscription<Message> {
+        let breathing = !self.config.accessibility.reduced_motion
+            && self
+                .on_break
+                .as_ref()
+                .is_some_and(|on_break| on_break.timer.breathing());
+        let tick = match self.tick_interval() {
+            // The breathing ring is animated
+            _ if breathing => window::frames().map(|_| Message::Tick),
+            Some(interval) => time::every(interval).map(|_| Message::Tick),
+            None => Subscription::none()
+        };
+
+        let requires_absence = self
+            .on_break
+            .as_ref()
+            .is_some_and(|on_break| on_break.timer.requires_absence());
+        let input = if requires_absence {
+            event::listen_with(|event, _, _| BreakMessage::from_input(event).map(Message::Break))
+        } else {
+            Subscription::none()
+        };
+
+        let dbus = if self.serves_dbus {
+            Subscription::run(dbus::run).map(Message::Dbus)
+        } else {
+            Subscription::none()
+        };
+
+        let color_scheme = if self.config.follows_color_scheme() {
+            Subscription::run(color_scheme::run).map(Message::ColorScheme)
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch([
-            window::frames().map(|_| Message::Tick),
-            iced::event::listen_with(Self::key_subscription)
+            tick,
+            input,
+            color_scheme,
+            window::close_events().map(Message::WindowClosed),
+            window::resize_events().map(|(id, size)| Message::WindowResized(id, size)),
+            Subscription::run(tray::run).map(Message::Tray),
+            dbus,
+            keyboard::listen()
+                .with(self.config.keybindings)
+                .filter_map(|(keybindings, event)| keybindings.action(event).map(Message::from))
         ])
     }
 }
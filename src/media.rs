@@ -0,0 +1,176 @@
+//! Pausing the playing media players for the duration of a break, on a dedicated thread since
+//! fading blocks and the players can't be sent across threads
+use std::{
+    fmt::Display,
+    mem,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration
+};
+
+use mpris::{PlaybackStatus, Player, PlayerFinder};
+
+use crate::config::MediaConfig;
+
+const FADE_STEPS: u32 = 20;
+
+#[derive(Debug, Clone, Copy)]
+enum Request {
+    Pause,
+    Resume
+}
+
+/// Does nothing if pausing media is disabled
+#[derive(Debug, Clone)]
+pub struct Media(Option<Sender<Request>>);
+
+impl Media {
+    pub fn spawn(config: MediaConfig) -> Self {
+        if !config.pause {
+            return Self(None);
+        }
+
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut paused = PausedPlayers::default();
+            for request in request_rx {
+                match request {
+                    Request::Pause => paused = PausedPlayers::pause_playing(config),
+                    Request::Resume => mem::take(&mut paused).resume(config)
+                }
+            }
+        });
+
+        Self(Some(request_tx))
+    }
+
+    /// Pauses the players playing at the start of a break
+    pub fn pause_playing(&self) {
+        self.request(Request::Pause);
+    }
+
+    /// Resumes the players paused by [`Media::pause_playing`]
+    pub fn resume(&self) {
+        self.request(Request::Resume);
+    }
+
+    fn request(&self, request: Request) {
+        if let Some(request_tx) = &self.0 {
+            // The media thread only ends with the app
+            let _ = request_tx.send(request);
+        }
+    }
+}
+
+/// Players paused at the start of a break, failures to control them are only reported on stderr
+#[derive(Default)]
+struct PausedPlayers(Vec<PausedPlayer>);
+
+struct PausedPlayer {
+    player: Player,
+    /// `None` if the player doesn't support changing its volume
+    volume: Option<f64>
+}
+
+fn warn(player: Option<&Player>, err: impl Display) {
+    match player {
+        Some(player) => eprintln!("Cannot control media player {}: {err}", player.identity()),
+        None => eprintln!("Cannot find media players: {err}")
+    }
+}
+
+impl MediaConfig {
+    fn allows(&self, player: &Player) -> bool {
+        let identity = player.identity();
+        let listed = |list: &[&str]| {
+            list.iter()
+                .any(|listed| listed.eq_ignore_ascii_case(identity))
+        };
+        (self.allow.is_empty() || listed(self.allow)) && !listed(self.deny)
+    }
+}
+
+impl PausedPlayers {
+    fn pause_playing(config: MediaConfig) -> Self {
+        let players = match PlayerFinder::new().map(|finder| finder.find_all()) {
+            Ok(Ok(players)) => players,
+            Ok(Err(err)) => {
+                warn(None, err);
+                return Self(Vec::new());
+            }
+            Err(err) => {
+                warn(None, err);
+                return Self(Vec::new());
+            }
+        };
+
+        let playing: Vec<_> = players
+            .into_iter()
+            .filter(|player| config.allows(player))
+            .filter(|player| match player.get_playback_status() {
+                Ok(status) => status == PlaybackStatus::Playing,
+                Err(err) => {
+                    warn(Some(player), err);
+                    false
+                }
+            })
+            .map(|player| PausedPlayer {
+                volume: player.checked_get_volume().ok().flatten(),
+                player
+            })
+            .collect();
+
+        fade(&playing, config.fade, |volume, progress| {
+            volume * (1.0 - progress)
+        });
+        let paused = playing
+            .into_iter()
+            .filter(|PausedPlayer { player, volume }| {
+                let result = player.pause().and_then(|_| match volume {
+                    Some(volume) => player.set_volume(*volume),
+                    None => Ok(())
+                });
+                result.inspect_err(|err| warn(Some(player), err)).is_ok()
+            })
+            .collect();
+
+        Self(paused)
+    }
+
+    fn resume(self, config: MediaConfig) {
+        let resumed: Vec<_> = self
+            .0
+            .into_iter()
+            .filter(|PausedPlayer { player, volume }| {
+                let result = match volume {
+                    Some(_) if !config.fade.is_zero() => player.set_volume(0.0),
+                    _ => Ok(())
+                }
+                .and_then(|_| player.play());
+                result.inspect_err(|err| warn(Some(player), err)).is_ok()
+            })
+            .collect();
+
+        fade(&resumed, config.fade, |volume, progress| volume * progress);
+    }
+}
+
+/// Gradually changes the volume of the players over `duration` by `volume_at(original, progress)`
+fn fade(players: &[PausedPlayer], duration: Duration, volume_at: impl Fn(f64, f64) -> f64) {
+    if duration.is_zero() {
+        return;
+    }
+
+    for step in 1..=FADE_STEPS {
+        let progress = step as f64 / FADE_STEPS as f64;
+        for PausedPlayer { player, volume } in players {
+            if let Some(volume) = volume
+                && let Err(err) = player.set_volume(volume_at(*volume, progress))
+            {
+                warn(Some(player), err);
+            }
+        }
+
+        thread::sleep(duration / FADE_STEPS);
+    }
+}
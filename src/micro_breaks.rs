@@ -0,0 +1,194 @@
+//! Short eye breaks on their own cadence between the pomodoro breaks, which they don't count as
+use std::time::Duration;
+
+use crate::{clock::Clock, config::MicroBreaksConfig, work_timer::WorkTimer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MicroBreakChange {
+    Start,
+    End
+}
+
+#[derive(Clone, Copy)]
+pub struct MicroBreaks {
+    config: MicroBreaksConfig,
+    /// Work time until the next micro-break, paused outside of work
+    until_next: WorkTimer,
+    /// The rest of the current micro-break
+    current: Option<WorkTimer>
+}
+
+impl MicroBreaks {
+    pub fn new(config: MicroBreaksConfig, clock: &impl Clock) -> Self {
+        Self {
+            config,
+            until_next: Self::cadence(config.interval, clock),
+            current: None
+        }
+    }
+
+    fn cadence(interval: Duration, clock: &impl Clock) -> WorkTimer {
+        let mut until_next = WorkTimer::new(interval, clock);
+        until_next.toggle_pause(clock);
+        until_next
+    }
+
+    /// `None` outside of micro-breaks
+    pub fn current(&self) -> Option<WorkTimer> {
+        self.current
+    }
+
+    pub fn dismissable(&self) -> bool {
+        self.config.dismissable
+    }
+
+    /// Counts down to the next micro-break only while `working`, and the current micro-break
+    /// regardless
+    pub fn tick(&mut self, working: bool, clock: &impl Clock) -> Option<MicroBreakChange> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        if let Some(current) = &mut self.current {
+            current.on_tick(clock);
+            if !current.duration_remaning().is_zero() {
+                return None;
+            }
+
+            return self.restart(clock);
+        }
+
+        if working == self.until_next.is_paused() {
+            self.until_next.toggle_pause(clock);
+        }
+
+        self.until_next.on_tick(clock);
+        if !self.until_next.duration_remaning().is_zero() {
+            return None;
+        }
+
+        self.current = Some(WorkTimer::new(self.config.duration, clock));
+        Some(MicroBreakChange::Start)
+    }
+
+    /// Ends the current micro-break early if there's one and that's allowed
+    pub fn dismiss(&mut self, clock: &impl Clock) -> Option<MicroBreakChange> {
+        if !self.config.dismissable || self.current.is_none() {
+            return None;
+        }
+
+        self.restart(clock)
+    }
+
+    /// Ends the current micro-break if any and starts the cadence over, e.g. on a pomodoro break
+    /// that rests the eyes anyway
+    pub fn restart(&mut self, clock: &impl Clock) -> Option<MicroBreakChange> {
+        self.until_next = Self::cadence(self.config.interval, clock);
+        self.current.take().map(|_| MicroBreakChange::End)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn micro_breaks(clock: &FakeClock, dismissable: bool) -> MicroBreaks {
+        MicroBreaks::new(
+            MicroBreaksConfig {
+                enabled: true,
+                interval: 20 * MINUTE,
+                duration: Duration::from_secs(20),
+                dismissable
+            },
+            clock
+        )
+    }
+
+    #[test]
+    fn starts_after_the_interval_of_work_and_ends_after_the_duration() {
+        let clock = FakeClock::new();
+        let mut micro_breaks = micro_breaks(&clock, true);
+        assert_eq!(micro_breaks.tick(true, &clock), None);
+        clock.advance(19 * MINUTE);
+        assert_eq!(micro_breaks.tick(true, &clock), None);
+        clock.advance(MINUTE);
+        assert_eq!(
+            micro_breaks.tick(true, &clock),
+            Some(MicroBreakChange::Start)
+        );
+
+        clock.advance(Duration::from_secs(19));
+        assert_eq!(micro_breaks.tick(true, &clock), None);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(micro_breaks.tick(true, &clock), Some(MicroBreakChange::End));
+        assert!(micro_breaks.current().is_none());
+    }
+
+    #[test]
+    fn does_not_count_down_outside_of_work() {
+        let clock = FakeClock::new();
+        let mut micro_breaks = micro_breaks(&clock, true);
+        micro_breaks.tick(true, &clock);
+        clock.advance(10 * MINUTE);
+        micro_breaks.tick(false, &clock);
+        clock.advance(60 * MINUTE);
+        assert_eq!(micro_breaks.tick(false, &clock), None);
+        assert_eq!(micro_breaks.tick(true, &clock), None);
+        clock.advance(10 * MINUTE);
+        assert_eq!(
+            micro_breaks.tick(true, &clock),
+            Some(MicroBreakChange::Start)
+        );
+    }
+
+    #[test]
+    fn dismissing_needs_to_be_allowed() {
+        let clock = FakeClock::new();
+        for dismissable in [true, false] {
+            let mut micro_breaks = micro_breaks(&clock, dismissable);
+            micro_breaks.tick(true, &clock);
+            clock.advance(20 * MINUTE);
+            micro_breaks.tick(true, &clock);
+            assert_eq!(
+                micro_breaks.dismiss(&clock),
+                dismissable.then_some(MicroBreakChange::End)
+            );
+            assert_eq!(micro_breaks.current().is_none(), dismissable);
+        }
+    }
+
+    #[test]
+    fn dismissing_without_a_micro_break_keeps_the_cadence() {
+        let clock = FakeClock::new();
+        let mut micro_breaks = micro_breaks(&clock, true);
+        micro_breaks.tick(true, &clock);
+        clock.advance(19 * MINUTE);
+        micro_breaks.tick(true, &clock);
+        assert_eq!(micro_breaks.dismiss(&clock), None);
+        clock.advance(MINUTE);
+        assert_eq!(
+            micro_breaks.tick(true, &clock),
+            Some(MicroBreakChange::Start)
+        );
+    }
+
+    #[test]
+    fn disabled_never_starts() {
+        let clock = FakeClock::new();
+        let mut micro_breaks = MicroBreaks::new(
+            MicroBreaksConfig {
+                enabled: false,
+                interval: MINUTE,
+                duration: MINUTE,
+                dismissable: true
+            },
+            &clock
+        );
+        micro_breaks.tick(true, &clock);
+        clock.advance(60 * MINUTE);
+        assert_eq!(micro_breaks.tick(true, &clock), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! Desktop notifications whose actions are sent back to the main window
+use std::time::Duration;
+
+use iced::Task;
+use notify_rust::Notification;
+use yanet::Result;
+
+#[derive(Debug, Clone, Copy)]
+pub enum NotificationAction {
+    BreakNow,
+    Snooze,
+    Pause
+}
+
+impl NotificationAction {
+    const ALL: [Self; 3] = [Self::BreakNow, Self::Snooze, Self::Pause];
+
+    fn identifier(self) -> &'static str {
+        match self {
+            NotificationAction::BreakNow => "break-now",
+            NotificationAction::Snooze => "snooze",
+            NotificationAction::Pause => "pause"
+        }
+    }
+
+    fn label(self, snooze_duration: Duration) -> String {
+        match self {
+            NotificationAction::BreakNow => "Start break now".to_string(),
+            NotificationAction::Snooze => match snooze_duration.as_secs() {
+                secs @ 0..60 => format!("Snooze {secs} s"),
+                secs => format!("Snooze {} min", secs / 60)
+            },
+            NotificationAction::Pause => "Pause".to_string()
+        }
+    }
+}
+
+/// The notification before a break, replaced rather than stacked when it's shown again
+#[derive(Debug, Default)]
+pub struct PreBreakNotification {
+    /// `None` while it isn't shown
+    id: Option<u32>,
+    /// How many times it's been shown, so that the actions of the replaced ones are ignored as they
+    /// share the id
+    shown: u64
+}
+
+impl PreBreakNotification {
+    /// Returns a task resolving to the action invoked by the user, which is only current if
+    /// [`PreBreakNotification::is_current`]
+    pub fn show(
+        &mut self,
+        duration_remaning: Duration,
+        snooze_duration: Duration
+    ) -> Result<Task<(u64, NotificationAction)>> {
+        let body = format!(
+            "Next break in {}:{:02}",
+            duration_remaning.as_secs() / 60,
+            duration_remaning.as_secs() % 60
+        );
+        let mut notification = Notification::new();
+        notification.summary("Annoyodoro").body(body.as_str());
+        for action in NotificationAction::ALL {
+            notification.action(action.identifier(), &action.label(snooze_duration));
+        }
+
+        if let Some(id) = self.id {
+            notification.id(id);
+        }
+
+        let handle = notification.show()?;
+        self.id = Some(handle.id());
+        self.shown += 1;
+        let shown = self.shown;
+        let action = smol::unblock(move || {
+            let mut invoked = None;
+            handle.wait_for_action(|identifier| {
+                invoked = NotificationAction::ALL
+                    .into_iter()
+                    .find(|action| action.identifier() == identifier)
+            });
+            invoked.map(|action| (shown, action))
+        });
+
+        Ok(Task::future(action).and_then(Task::done))
+    }
+
+    pub fn is_current(&self, shown: u64) -> bool {
+        self.id.is_some() && self.shown == shown
+    }
+
+    /// Closes the notification if it's shown, failures are only reported on stderr
+    pub fn close<T: Send + 'static>(&mut self) -> Task<T> {
+        let Some(id) = self.id.take() else {
+            return Task::none();
+        };
+
+        Task::future(async move {
+            if let Err(err) = close(id).await {
+                eprintln!("Cannot close the pre-break notification: {err}");
+            }
+        })
+        .discard()
+    }
+}
+
+async fn close(id: u32) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "CloseNotification",
+            &id
+        )
+        .await?;
+    Ok(())
+}
+
+/// Stands in for the micro-break overlay without layer-shell, a window would take the focus
+pub fn show_micro_break(duration: Duration) -> Result<()> {
+    Notification::new()
+        .summary("Annoyodoro")
+        .body(&format!(
+            "Look at something far away for {} seconds",
+            duration.as_secs()
+        ))
+        .timeout(duration)
+        .show()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snoozes_below_a_minute_in_seconds() {
+        let label = |secs| NotificationAction::Snooze.label(Duration::from_secs(secs));
+        assert_eq!(label(30), "Snooze 30 s");
+        assert_eq!(label(300), "Snooze 5 min");
+    }
+}
@@ -0,0 +1,454 @@
+//! The pomodoro cycle without any side effects, the frontends feed it events and carry out the
+//! [`Effect`]s it returns
+use std::time::Duration;
+
+use crate::{clock::Clock, config::PomodoroConfig, work_timer::WorkTimer};
+
+#[derive(Clone, Copy)]
+pub struct Session {
+    pomodoro: PomodoroConfig,
+    phase: SessionPhase,
+    long_break_in: u16,
+    last_work_session: bool
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SessionPhase {
+    WaitingForGoal,
+    Work {
+        work_timer: WorkTimer,
+        /// Reset by snoozing so that the notification is shown again
+        shown_notification: bool
+    },
+    /// The frontend counts the break down and submits the goal of the next work session once it's
+    /// over
+    Break {
+        long_break: bool
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    Tick,
+    TogglePause,
+    ToggleLastWorkSession,
+    SetLastWorkSession(bool),
+    BreakNow,
+    Snooze,
+    GoalSubmitted(String),
+    Quit
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    PreBreakNotification {
+        remaining: Duration
+    },
+    Paused,
+    Resumed,
+    BreakStart {
+        long_break: bool
+    },
+    BreakEnd {
+        long_break: bool
+    },
+    /// `first` is set for the first work session since annoyodoro was started
+    WorkStart {
+        goal: String,
+        first: bool
+    },
+    LastWorkSessionOver,
+    /// Annoyodoro should exit
+    SessionEnd
+}
+
+impl Session {
+    pub fn new(pomodoro: PomodoroConfig) -> Self {
+        Session {
+            pomodoro,
+            phase: SessionPhase::WaitingForGoal,
+            long_break_in: pomodoro.long_break_each.into(),
+            last_work_session: false
+        }
+    }
+
+    pub fn phase(&self) -> SessionPhase {
+        self.phase
+    }
+
+    pub fn long_break_in(&self) -> u16 {
+        self.long_break_in
+    }
+
+    pub fn last_work_session(&self) -> bool {
+        self.last_work_session
+    }
+
+    /// `None` outside of work sessions
+    pub fn work_timer(&self) -> Option<WorkTimer> {
+        match self.phase {
+            SessionPhase::Work { work_timer, .. } => Some(work_timer),
+            SessionPhase::WaitingForGoal | SessionPhase::Break { .. } => None
+        }
+    }
+
+    pub fn handle(&mut self, event: SessionEvent, clock: &impl Clock) -> Vec<Effect> {
+        match (event, &mut self.phase) {
+            (
+                SessionEvent::Tick,
+                SessionPhase::Work {
+                    work_timer,
+                    shown_notification
+                }
+            ) => {
+                work_timer.on_tick(clock);
+                let remaining = work_timer.duration_remaning();
+                let mut effects = Vec::new();
+                if remaining <= self.pomodoro.notification_duration && !*shown_notification {
+                    *shown_notification = true;
+                    effects.push(Effect::PreBreakNotification { remaining });
+                }
+
+                if !remaining.is_zero() {
+                    return effects;
+                }
+
+                if self.last_work_session {
+                    effects.extend([Effect::LastWorkSessionOver, Effect::SessionEnd]);
+                } else {
+                    effects.push(self.start_break());
+                }
+
+                effects
+            }
+            (SessionEvent::TogglePause, SessionPhase::Work { work_timer, .. }) => {
+                work_timer.toggle_pause(clock);
+                if work_timer.is_paused() {
+                    vec![Effect::Paused]
+                } else {
+                    vec![Effect::Resumed]
+                }
+            }
+            (SessionEvent::ToggleLastWorkSession, SessionPhase::Work { .. }) => {
+                self.last_work_session = !self.last_work_session;
+                Vec::new()
+            }
+            (SessionEvent::SetLastWorkSession(last_work_session), SessionPhase::Work { .. }) => {
+                self.last_work_session = last_work_session;
+                Vec::new()
+            }
+            (SessionEvent::BreakNow, SessionPhase::Work { .. }) => vec![self.start_break()],
+            (
+                SessionEvent::Snooze,
+                SessionPhase::Work {
+                    work_timer,
+                    shown_notification
+                }
+            ) => {
+                work_timer.snooze(self.pomodoro.snooze_duration);
+                *shown_notification = false;
+                Vec::new()
+            }
+            (SessionEvent::GoalSubmitted(goal), SessionPhase::WaitingForGoal) => {
+                self.start_work(clock);
+                vec![Effect::WorkStart { goal, first: true }]
+            }
+            (SessionEvent::GoalSubmitted(goal), SessionPhase::Break { long_break }) => {
+                let long_break = *long_break;
+                self.start_work(clock);
+                vec![
+                    Effect::BreakEnd { long_break },
+                    Effect::WorkStart { goal, first: false },
+                ]
+            }
+            (SessionEvent::Quit, SessionPhase::Break { long_break }) => {
+                vec![
+                    Effect::BreakEnd {
+                        long_break: *long_break
+                    },
+                    Effect::SessionEnd,
+                ]
+            }
+            (SessionEvent::Quit, SessionPhase::WaitingForGoal | SessionPhase::Work { .. }) => {
+                vec![Effect::SessionEnd]
+            }
+            (SessionEvent::GoalSubmitted(_), SessionPhase::Work { .. }) => Vec::new(),
+            (
+                SessionEvent::Tick
+                | SessionEvent::TogglePause
+                | SessionEvent::ToggleLastWorkSession
+                | SessionEvent::SetLastWorkSession(_)
+                | SessionEvent::BreakNow
+                | SessionEvent::Snooze,
+                SessionPhase::WaitingForGoal | SessionPhase::Break { .. }
+            ) => Vec::new()
+        }
+    }
+
+    fn start_work(&mut self, clock: &impl Clock) {
+        self.phase = SessionPhase::Work {
+            work_timer: WorkTimer::new(self.pomodoro.work_duration, clock),
+            shown_notification: false
+        };
+    }
+
+    fn start_break(&mut self) -> Effect {
+        self.long_break_in -= 1;
+        let long_break = self.long_break_in == 0;
+        if long_break {
+            self.long_break_in = self.pomodoro.long_break_each.into();
+        }
+
+        self.phase = SessionPhase::Break { long_break };
+        Effect::BreakStart { long_break }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZero;
+
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn pomodoro() -> PomodoroConfig {
+        PomodoroConfig {
+            break_duration: 5 * MINUTE,
+            work_duration: 25 * MINUTE,
+            long_break_duration: 15 * MINUTE,
+            notification_duration: MINUTE,
+            snooze_duration: 2 * MINUTE,
+            long_break_each: NonZero::new(3).unwrap(),
+            daily_target: 8
+        }
+    }
+
+    fn working(clock: &FakeClock) -> Session {
+        let mut session = Session::new(pomodoro());
+        session.handle(
+            SessionEvent::GoalSubmitted("Write tests".to_string()),
+            clock
+        );
+        session
+    }
+
+    fn tick_after(session: &mut Session, clock: &FakeClock, duration: Duration) -> Vec<Effect> {
+        clock.advance(duration);
+        session.handle(SessionEvent::Tick, clock)
+    }
+
+    #[test]
+    fn first_goal_starts_work() {
+        let clock = FakeClock::new();
+        let mut session = Session::new(pomodoro());
+        assert!(matches!(session.phase(), SessionPhase::WaitingForGoal));
+        assert_eq!(tick_after(&mut session, &clock, MINUTE), []);
+
+        let effects = session.handle(
+            SessionEvent::GoalSubmitted("Write tests".to_string()),
+            &clock
+        );
+        assert_eq!(
+            effects,
+            [Effect::WorkStart {
+                goal: "Write tests".to_string(),
+                first: true
+            }]
+        );
+        assert_eq!(
+            session.work_timer().unwrap().duration_remaning(),
+            25 * MINUTE
+        );
+        assert_eq!(session.long_break_in(), 3);
+    }
+
+    #[test]
+    fn pre_break_notification_is_shown_once() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        assert_eq!(tick_after(&mut session, &clock, 23 * MINUTE), []);
+        assert_eq!(
+            tick_after(&mut session, &clock, MINUTE),
+            [Effect::PreBreakNotification { remaining: MINUTE }]
+        );
+        assert_eq!(
+            tick_after(&mut session, &clock, Duration::from_secs(30)),
+            []
+        );
+    }
+
+    #[test]
+    fn snoozing_shows_the_notification_again() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        tick_after(&mut session, &clock, 24 * MINUTE);
+        assert_eq!(session.handle(SessionEvent::Snooze, &clock), []);
+        assert_eq!(
+            session.work_timer().unwrap().duration_remaning(),
+            3 * MINUTE
+        );
+        assert_eq!(tick_after(&mut session, &clock, MINUTE), []);
+        assert_eq!(
+            tick_after(&mut session, &clock, MINUTE),
+            [Effect::PreBreakNotification { remaining: MINUTE }]
+        );
+    }
+
+    #[test]
+    fn break_starts_when_work_is_over() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        tick_after(&mut session, &clock, 24 * MINUTE);
+        assert_eq!(
+            tick_after(&mut session, &clock, MINUTE),
+            [Effect::BreakStart { long_break: false }]
+        );
+        assert!(matches!(
+            session.phase(),
+            SessionPhase::Break { long_break: false }
+        ));
+        assert_eq!(session.long_break_in(), 2);
+    }
+
+    #[test]
+    fn notification_and_break_can_happen_on_the_same_tick() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        assert_eq!(
+            tick_after(&mut session, &clock, 30 * MINUTE),
+            [
+                Effect::PreBreakNotification {
+                    remaining: Duration::ZERO
+                },
+                Effect::BreakStart { long_break: false }
+            ]
+        );
+    }
+
+    #[test]
+    fn every_nth_break_is_long() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        let mut long_breaks = Vec::new();
+        for _ in 0..6 {
+            let Some(Effect::BreakStart { long_break }) =
+                session.handle(SessionEvent::BreakNow, &clock).pop()
+            else {
+                panic!("Break didn't start");
+            };
+            long_breaks.push(long_break);
+            session.handle(SessionEvent::GoalSubmitted("Next".to_string()), &clock);
+        }
+
+        assert_eq!(long_breaks, [false, false, true, false, false, true]);
+        assert_eq!(session.long_break_in(), 3);
+    }
+
+    #[test]
+    fn break_end_keeps_the_last_work_session() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        session.handle(SessionEvent::BreakNow, &clock);
+        let effects = session.handle(SessionEvent::GoalSubmitted("Next".to_string()), &clock);
+        assert_eq!(
+            effects,
+            [
+                Effect::BreakEnd { long_break: false },
+                Effect::WorkStart {
+                    goal: "Next".to_string(),
+                    first: false
+                }
+            ]
+        );
+
+        session.handle(SessionEvent::ToggleLastWorkSession, &clock);
+        session.handle(SessionEvent::BreakNow, &clock);
+        session.handle(SessionEvent::GoalSubmitted("Last".to_string()), &clock);
+        assert!(session.last_work_session());
+    }
+
+    #[test]
+    fn last_work_session_ends_the_session() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        session.handle(SessionEvent::SetLastWorkSession(true), &clock);
+        tick_after(&mut session, &clock, 24 * MINUTE);
+        assert_eq!(
+            tick_after(&mut session, &clock, MINUTE),
+            [Effect::LastWorkSessionOver, Effect::SessionEnd]
+        );
+    }
+
+    #[test]
+    fn break_now_starts_a_break_even_in_the_last_work_session() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        session.handle(SessionEvent::ToggleLastWorkSession, &clock);
+        assert_eq!(
+            session.handle(SessionEvent::BreakNow, &clock),
+            [Effect::BreakStart { long_break: false }]
+        );
+    }
+
+    #[test]
+    fn work_events_are_ignored_outside_of_work() {
+        let clock = FakeClock::new();
+        let mut session = Session::new(pomodoro());
+        for event in [
+            SessionEvent::BreakNow,
+            SessionEvent::TogglePause,
+            SessionEvent::Snooze,
+            SessionEvent::ToggleLastWorkSession
+        ] {
+            assert_eq!(session.handle(event, &clock), []);
+        }
+
+        let mut session = working(&clock);
+        session.handle(SessionEvent::BreakNow, &clock);
+        assert_eq!(session.handle(SessionEvent::BreakNow, &clock), []);
+        assert_eq!(
+            session.handle(SessionEvent::ToggleLastWorkSession, &clock),
+            []
+        );
+        assert_eq!(session.long_break_in(), 2);
+        assert!(!session.last_work_session());
+    }
+
+    #[test]
+    fn pausing_stops_the_countdown() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        clock.advance(MINUTE);
+        assert_eq!(
+            session.handle(SessionEvent::TogglePause, &clock),
+            [Effect::Paused]
+        );
+        assert_eq!(tick_after(&mut session, &clock, 30 * MINUTE), []);
+        assert_eq!(
+            session.handle(SessionEvent::TogglePause, &clock),
+            [Effect::Resumed]
+        );
+        assert_eq!(
+            session.work_timer().unwrap().duration_remaning(),
+            24 * MINUTE
+        );
+    }
+
+    #[test]
+    fn quitting_during_a_break_ends_it() {
+        let clock = FakeClock::new();
+        let mut session = working(&clock);
+        assert_eq!(
+            session.handle(SessionEvent::Quit, &clock),
+            [Effect::SessionEnd]
+        );
+
+        session.handle(SessionEvent::BreakNow, &clock);
+        assert_eq!(
+            session.handle(SessionEvent::Quit, &clock),
+            [Effect::BreakEnd { long_break: false }, Effect::SessionEnd]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Sound cues for phase transitions, played on a dedicated audio thread
+use std::{
+    fs::File,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration
+};
+
+use rodio::{
+    Decoder, OutputStreamBuilder, Sink, Source,
+    mixer::Mixer,
+    source::{SineWave, Zero}
+};
+use smol::{Timer, channel, future};
+use yanet::Result;
+
+use crate::config::{SoundConfig, SoundsConfig};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Cue {
+    PreBreak,
+    BreakStart,
+    BreakEnd,
+    SessionEnd
+}
+
+impl Cue {
+    /// Tones of the bundled sound as frequency in Hz and length in ms, a frequency of 0 is a pause
+    fn bundled(self) -> &'static [(f32, u64)] {
+        match self {
+            Cue::PreBreak => &[(880.0, 120), (0.0, 80), (880.0, 120)],
+            Cue::BreakStart => &[(659.3, 180), (523.3, 180), (440.0, 320)],
+            Cue::BreakEnd => &[(440.0, 180), (523.3, 180), (659.3, 180), (880.0, 320)],
+            Cue::SessionEnd => &[(880.0, 200), (659.3, 200), (523.3, 200), (440.0, 500)]
+        }
+    }
+}
+
+impl SoundsConfig {
+    fn sound(&self, cue: Cue) -> SoundConfig {
+        match cue {
+            Cue::PreBreak => self.pre_break,
+            Cue::BreakStart => self.break_start,
+            Cue::BreakEnd => self.break_end,
+            Cue::SessionEnd => self.session_end
+        }
+    }
+}
+
+/// How long [`Sounds::play_until_end`] waits at most, custom sounds can be long
+const MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// A cue and what to notify once it's been played, if anything waits for it
+type Request = (Cue, Option<channel::Sender<()>>);
+
+/// Does nothing if sounds are disabled or there's no audio output
+#[derive(Debug, Clone)]
+pub struct Sounds(Option<Sender<Request>>);
+
+impl Sounds {
+    pub fn spawn(config: SoundsConfig) -> Self {
+        if !config.enabled {
+            return Self(None);
+        }
+
+        let (cue_tx, cue_rx) = mpsc::channel::<Request>();
+        thread::spawn(move || {
+            let mut stream = match OutputStreamBuilder::open_default_stream() {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("Cannot open audio output, sound cues are disabled: {err}");
+                    return;
+                }
+            };
+
+            stream.log_on_drop(false);
+            for (cue, played) in cue_rx {
+                if let Err(err) = play(stream.mixer(), config.sound(cue), cue, played.is_some()) {
+                    eprintln!("Cannot play sound cue {cue:?}: {err}");
+                }
+
+                if let Some(played) = played {
+                    let _ = played.send_blocking(());
+                }
+            }
+        });
+
+        Self(Some(cue_tx))
+    }
+
+    #[cfg(test)]
+    pub fn muted() -> Self {
+        Self(None)
+    }
+
+    pub fn play(&self, cue: Cue) {
+        if let Some(cue_tx) = &self.0 {
+            // The audio thread is gone if there's no audio output
+            let _ = cue_tx.send((cue, None));
+        }
+    }
+
+    /// Plays `cue` and waits until it's over, e.g. before exiting
+    pub fn play_until_end(&self, cue: Cue) -> impl Future<Output = ()> + use<> {
+        let played = self.0.as_ref().and_then(|cue_tx| {
+            let (played_tx, played_rx) = channel::bounded(1);
+            cue_tx.send((cue, Some(played_tx))).ok().map(|_| played_rx)
+        });
+
+        async move {
+            let Some(played) = played else {
+                return;
+            };
+
+            future::or(
+                async {
+                    // Fails right away if the audio thread is gone
+                    let _ = played.recv().await;
+                },
+                async {
+                    Timer::after(MAX_WAIT).await;
+                }
+            )
+            .await;
+        }
+    }
+}
+
+/// Blocks until the sound is over if `wait`
+fn play(mixer: &Mixer, sound: SoundConfig, cue: Cue, wait: bool) -> Result<()> {
+    let sink = Sink::connect_new(mixer);
+    sink.set_volume(sound.volume);
+    match sound.file {
+        Some(path) => sink.append(Decoder::try_from(File::open(path)?)?),
+        None => {
+            for &(frequency, millis) in cue.bundled() {
+                let duration = Duration::from_millis(millis);
+                if frequency == 0.0 {
+                    sink.append(Zero::new(1, 48000).take_duration(duration));
+                } else {
+                    sink.append(
+                        SineWave::new(frequency)
+                            .take_duration(duration)
+                            .fade_in(Duration::from_millis(10))
+                    );
+                }
+            }
+        }
+    }
+
+    if wait {
+        sink.sleep_until_end();
+    } else {
+        sink.detach();
+    }
+
+    Ok(())
+}
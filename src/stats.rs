@@ -1,7 +1,9 @@
-//! Stats are not yet displayed but they're here for forwards compatibility
 use std::{collections::BTreeMap, fs, fs::File, io::BufWriter, path::PathBuf};
 
-use bincode::{Decode, Encode, decode_from_slice, encode_into_std_write};
+use bincode::{
+    Decode, Encode, de::Decoder, decode_from_slice, encode_into_std_write, error::DecodeError,
+    serde::Compat
+};
 use jiff::{
     Zoned,
     civil::{Date, DateTime}
@@ -14,7 +16,7 @@ pub struct StatsManager {
     stats: Stats
 }
 
-#[derive(Encode, Decode, Default)]
+#[derive(Encode, Default)]
 struct Stats {
     #[bincode(with_serde)]
     work_goals: Vec<(DateTime, String)>,
@@ -23,7 +25,31 @@ struct Stats {
     week: CountMap<Week>,
     month: CountMap<Month>,
     year: CountMap<Year>,
-    all_time: Count
+    all_time: Count,
+    /// Breaks in which the suggested activity was done, by day
+    #[bincode(with_serde)]
+    suggestions_done: BTreeMap<Date, u32>
+}
+
+// Not derived so that the stats saved before a field was appended can still be decoded
+impl<Context> Decode<Context> for Stats {
+    fn decode<D: Decoder<Context = Context>>(
+        decoder: &mut D
+    ) -> std::result::Result<Self, DecodeError> {
+        Ok(Self {
+            work_goals: Compat::decode(decoder)?.0,
+            day: Compat::decode(decoder)?.0,
+            week: Decode::decode(decoder)?,
+            month: Decode::decode(decoder)?,
+            year: Decode::decode(decoder)?,
+            all_time: Decode::decode(decoder)?,
+            suggestions_done: match Compat::decode(decoder) {
+                Ok(Compat(suggestions_done)) => suggestions_done,
+                Err(DecodeError::UnexpectedEnd { .. }) => BTreeMap::new(),
+                Err(err) => return Err(err)
+            }
+        })
+    }
 }
 
 #[derive(Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -73,9 +99,9 @@ impl<K: Ord> Default for CountMap<K> {
 }
 
 #[derive(Encode, Decode, Serialize, Deserialize, Clone, Copy, Default)]
-struct Count {
-    sessions: u32,
-    pomodori: u32
+pub struct Count {
+    pub sessions: u32,
+    pub pomodori: u32
 }
 
 impl<K: From<Date> + Ord> CountMap<K> {
@@ -161,6 +187,14 @@ impl StatsManager {
         stats.all_time.sessions += 1;
     }
 
+    pub fn increment_suggestions_done_count(&mut self) {
+        *self
+            .stats
+            .suggestions_done
+            .entry(self.current_date)
+            .or_default() += 1;
+    }
+
     pub fn add_work_goal(&mut self, goal: String) {
         self.stats.work_goals.push((Zoned::now().datetime(), goal))
     }
@@ -182,4 +216,174 @@ impl StatsManager {
     pub fn pomodori_daily(&self) -> u32 {
         self.stats.day.get(self.current_date).pomodori
     }
+
+    pub fn work_goals_today(&self) -> usize {
+        self.stats
+            .work_goals
+            .iter()
+            .filter(|(time, _)| time.date() == self.current_date)
+            .count()
+    }
+
+    /// Consecutive days with pomodori that end yesterday
+    pub fn streak_until_yesterday(&self) -> u32 {
+        let mut streak = 0;
+        let mut date = self.current_date;
+        while let Ok(yesterday) = date.yesterday()
+            && self.stats.day.get(yesterday).pomodori > 0
+        {
+            streak += 1;
+            date = yesterday;
+        }
+
+        streak
+    }
+
+    /// Counts for today, this week, this month, this year and all time
+    pub fn counts(&self) -> [(&'static str, Count); 5] {
+        let stats = &self.stats;
+        let date = self.current_date;
+        [
+            ("Today", stats.day.get(date)),
+            ("This week", stats.week.get(date)),
+            ("This month", stats.month.get(date)),
+            ("This year", stats.year.get(date)),
+            ("All time", stats.all_time)
+        ]
+    }
+
+    /// Breaks in which the suggested activity was done, in the same periods as [`Self::counts`]
+    pub fn suggestions_done(&self) -> [u32; 5] {
+        let date = self.current_date;
+        let done = &self.stats.suggestions_done;
+        let sum = |same_period: fn(Date, Date) -> bool| {
+            done.iter()
+                .filter(|(day, _)| same_period(**day, date))
+                .map(|(_, count)| count)
+                .sum()
+        };
+
+        [
+            sum(|day, date| day == date),
+            sum(|day, date| Week::from(day) == Week::from(date)),
+            sum(|day, date| Month::from(day) == Month::from(date)),
+            sum(|day, date| Year::from(day) == Year::from(date)),
+            done.values().sum()
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode::{config, encode_to_vec};
+    use jiff::civil::date;
+
+    use super::*;
+
+    /// The layout before `suggestions_done` was appended
+    #[derive(Encode)]
+    struct StatsBeforeSuggestions {
+        #[bincode(with_serde)]
+        work_goals: Vec<(DateTime, String)>,
+        #[bincode(with_serde)]
+        day: CountMap<Date>,
+        week: CountMap<Week>,
+        month: CountMap<Month>,
+        year: CountMap<Year>,
+        all_time: Count
+    }
+
+    #[test]
+    fn decodes_stats_saved_before_suggestions() {
+        let today = date(2026, 10, 18);
+        let mut day = CountMap::default();
+        day.increment_pomodori(today);
+        let old = StatsBeforeSuggestions {
+            work_goals: vec![(today.at(9, 0, 0, 0), "Write tests".to_string())],
+            day,
+            week: CountMap::default(),
+            month: CountMap::default(),
+            year: CountMap::default(),
+            all_time: Count {
+                sessions: 1,
+                pomodori: 1
+            }
+        };
+
+        let bytes = encode_to_vec(&old, config::standard()).unwrap();
+        let (stats, _): (Stats, _) = decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(stats.work_goals, old.work_goals);
+        assert_eq!(stats.day.get(today).pomodori, 1);
+        assert_eq!(stats.all_time.sessions, 1);
+        assert!(stats.suggestions_done.is_empty());
+    }
+
+    fn stats_manager(current_date: Date, pomodori: &[Date], goals: &[DateTime]) -> StatsManager {
+        let mut stats = StatsManager {
+            current_date,
+            stats: Stats::default()
+        };
+        for date in pomodori {
+            stats.stats.day.increment_pomodori(*date);
+        }
+
+        stats.stats.work_goals = goals
+            .iter()
+            .map(|time| (*time, "Write tests".to_string()))
+            .collect();
+        stats
+    }
+
+    #[test]
+    fn streak_ends_at_a_gap() {
+        let today = date(2026, 10, 18);
+        let stats = stats_manager(
+            today,
+            &[
+                date(2026, 10, 13),
+                date(2026, 10, 15),
+                date(2026, 10, 16),
+                date(2026, 10, 17),
+                today
+            ],
+            &[]
+        );
+        assert_eq!(stats.streak_until_yesterday(), 3);
+    }
+
+    #[test]
+    fn streak_needs_yesterday() {
+        let today = date(2026, 10, 18);
+        assert_eq!(
+            stats_manager(today, &[date(2026, 10, 16), today], &[]).streak_until_yesterday(),
+            0
+        );
+        assert_eq!(stats_manager(today, &[], &[]).streak_until_yesterday(), 0);
+    }
+
+    #[test]
+    fn counts_only_the_goals_of_today() {
+        let today = date(2026, 10, 18);
+        let stats = stats_manager(
+            today,
+            &[],
+            &[
+                date(2026, 10, 17).at(23, 59, 0, 0),
+                today.at(0, 0, 0, 0),
+                today.at(9, 30, 0, 0)
+            ]
+        );
+        assert_eq!(stats.work_goals_today(), 2);
+        assert_eq!(stats_manager(today, &[], &[]).work_goals_today(), 0);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let today = date(2026, 10, 18);
+        let mut stats = Stats::default();
+        stats.suggestions_done.insert(today, 2);
+        let bytes = encode_to_vec(&stats, config::standard()).unwrap();
+        let (decoded, _): (Stats, _) = decode_from_slice(&bytes, config::standard()).unwrap();
+        assert_eq!(decoded.suggestions_done, stats.suggestions_done);
+    }
 }
@@ -0,0 +1,163 @@
+//! Activities suggested on the break overlay, the built-in ones plus the ones from the user's file
+use std::{fs, path::Path};
+
+use figment::{
+    Figment,
+    providers::{Data, Toml}
+};
+use serde::Deserialize;
+use yanet::{Result, ResultExt};
+
+use crate::config::SuggestionsConfig;
+
+const SHORT: &[&str] = &[
+    "Stretch your neck and shoulders",
+    "Drink a glass of water",
+    "Look at something at least 20 meters away",
+    "Stand up and walk around the room",
+    "Close your eyes and let them rest",
+    "Roll your wrists and stretch your fingers",
+    "Take a few slow, deep breaths"
+];
+
+const LONG: &[&str] = &[
+    "Go for a walk outside",
+    "Make yourself a healthy snack",
+    "Do a few squats and push-ups",
+    "Tidy up your desk",
+    "Stretch your whole body",
+    "Step outside and get some daylight",
+    "Call or text a friend"
+];
+
+/// The suggestions of one break, starting where the previous break of the same kind left off
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rotation {
+    list: &'static [&'static str],
+    index: usize
+}
+
+impl Rotation {
+    fn new(list: &'static [&'static str]) -> Self {
+        Self { list, index: 0 }
+    }
+
+    /// `None` if suggestions are disabled
+    pub fn current(&self) -> Option<&'static str> {
+        self.list.get(self.index).copied()
+    }
+
+    pub fn advance(&mut self) {
+        if !self.list.is_empty() {
+            self.index = (self.index + 1) % self.list.len();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Suggestions {
+    short: Rotation,
+    long: Rotation
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CustomSuggestions {
+    #[serde(default)]
+    short: Vec<String>,
+    #[serde(default)]
+    long: Vec<String>
+}
+
+impl CustomSuggestions {
+    fn load(path: &Path) -> Result<Self> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            return Ok(Figment::from(Data::<Toml>::file_exact(path)).extract()?);
+        }
+
+        let markdown = fs::read_to_string(path)
+            .wrap_err_with(|_| format!("Cannot open {}", path.to_string_lossy()))?;
+        Ok(Self::parse_markdown(&markdown))
+    }
+
+    /// Takes the list items under the headings containing "short" and "long"
+    fn parse_markdown(markdown: &str) -> Self {
+        let mut file = Self::default();
+        // Whether the items are for long breaks, `None` under other headings
+        let mut long_break = None;
+        for line in markdown.lines().map(str::trim) {
+            if let Some(heading) = line.strip_prefix('#') {
+                let heading = heading.to_lowercase();
+                long_break = if heading.contains("short") {
+                    Some(false)
+                } else if heading.contains("long") {
+                    Some(true)
+                } else {
+                    None
+                };
+                continue;
+            }
+
+            let Some(item) = ["- ", "* ", "+ "]
+                .into_iter()
+                .find_map(|marker| line.strip_prefix(marker))
+            else {
+                continue;
+            };
+
+            match long_break {
+                Some(true) => file.long.push(item.trim().to_string()),
+                Some(false) => file.short.push(item.trim().to_string()),
+                None => {}
+            }
+        }
+
+        file
+    }
+}
+
+fn leak(built_in: &[&'static str], custom: Vec<String>) -> &'static [&'static str] {
+    built_in
+        .iter()
+        .copied()
+        .chain(custom.into_iter().map(|suggestion| &*suggestion.leak()))
+        .collect::<Vec<_>>()
+        .leak()
+}
+
+impl Suggestions {
+    pub fn load(config: SuggestionsConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self {
+                short: Rotation::default(),
+                long: Rotation::default()
+            });
+        }
+
+        let file = match config.file {
+            Some(path) => CustomSuggestions::load(path)?,
+            None => CustomSuggestions::default()
+        };
+
+        Ok(Self {
+            short: Rotation::new(leak(SHORT, file.short)),
+            long: Rotation::new(leak(LONG, file.long))
+        })
+    }
+
+    /// Advances the rotation, so that the next break of the same kind suggests something else
+    pub fn for_break(&mut self, long_break: bool) -> Rotation {
+        let rotation = if long_break {
+            &mut self.long
+        } else {
+            &mut self.short
+        };
+
+        let current = *rotation;
+        rotation.advance();
+        current
+    }
+}
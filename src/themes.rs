@@ -0,0 +1,96 @@
+//! Built-in color presets, selected with `theme` in the config
+use iced::{Color, color};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colors {
+    pub background: Color,
+    pub text: Color,
+    pub accent: Color,
+    pub danger: Color
+}
+
+pub const NAMES: &[&str] = &[
+    "catppuccin-mocha",
+    "catppuccin-macchiato",
+    "catppuccin-frappe",
+    "catppuccin-latte",
+    "gruvbox-dark",
+    "gruvbox-light",
+    "nord",
+    "solarized-dark",
+    "solarized-light",
+    "high-contrast"
+];
+
+pub fn preset(name: &str) -> Option<Colors> {
+    let [background, text, accent, danger] = match name {
+        "catppuccin-mocha" => [
+            color!(0x1e1e2e),
+            color!(0xcdd6f4),
+            color!(0xcba6f7),
+            color!(0xf38ba8)
+        ],
+        "catppuccin-macchiato" => [
+            color!(0x24273a),
+            color!(0xcad3f5),
+            color!(0xc6a0f6),
+            color!(0xed8796)
+        ],
+        "catppuccin-frappe" => [
+            color!(0x303446),
+            color!(0xc6d0f5),
+            color!(0xca9ee6),
+            color!(0xe78284)
+        ],
+        "catppuccin-latte" => [
+            color!(0xeff1f5),
+            color!(0x4c4f69),
+            color!(0x8839ef),
+            color!(0xd20f39)
+        ],
+        "gruvbox-dark" => [
+            color!(0x282828),
+            color!(0xebdbb2),
+            color!(0xfabd2f),
+            color!(0xfb4934)
+        ],
+        "gruvbox-light" => [
+            color!(0xfbf1c7),
+            color!(0x3c3836),
+            color!(0xb57614),
+            color!(0x9d0006)
+        ],
+        "nord" => [
+            color!(0x2e3440),
+            color!(0xeceff4),
+            color!(0x88c0d0),
+            color!(0xbf616a)
+        ],
+        "solarized-dark" => [
+            color!(0x002b36),
+            color!(0x93a1a1),
+            color!(0x268bd2),
+            color!(0xdc322f)
+        ],
+        "solarized-light" => [
+            color!(0xfdf6e3),
+            color!(0x586e75),
+            color!(0x268bd2),
+            color!(0xdc322f)
+        ],
+        "high-contrast" => [
+            color!(0x000000),
+            color!(0xffffff),
+            color!(0xffff00),
+            color!(0xff5555)
+        ],
+        _ => return None
+    };
+
+    Some(Colors {
+        background,
+        text,
+        accent,
+        danger
+    })
+}
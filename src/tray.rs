@@ -0,0 +1,249 @@
+//! StatusNotifierItem tray icon, keeps the timer reachable while the main window is closed
+use std::{
+    f32::consts::PI,
+    fmt::{self, Debug}
+};
+
+use iced::{
+    Color, Task,
+    futures::{SinkExt, Stream, channel::mpsc::Sender, future},
+    stream
+};
+use ksni::{
+    Handle, Icon, MenuItem, ToolTip, Tray, TrayMethods,
+    menu::{CheckmarkItem, StandardItem}
+};
+
+const ICON_SIZE: i32 = 32;
+/// The ring in the icon is only redrawn in these steps
+pub const ICON_STEPS: u8 = 64;
+
+#[derive(Debug, Clone, Copy)]
+pub enum TrayAction {
+    ShowWindow,
+    TogglePause,
+    ToggleLastWorkSession,
+    BreakNow,
+    OpenStats,
+    Quit
+}
+
+#[derive(Debug, Clone)]
+pub enum TrayEvent {
+    Ready(TrayHandle),
+    Action(TrayAction)
+}
+
+/// What the tray shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrayState {
+    /// Of the work session or the break, `None` until the first work goal is submitted
+    pub remaining_secs: Option<u64>,
+    pub on_break: bool,
+    /// Out of [`ICON_STEPS`]
+    pub progress: u8,
+    pub paused: bool,
+    pub last_work_session: bool,
+    /// Colors of the ring as RGBA8, so that they can be compared
+    pub accent: [u8; 4],
+    pub track: [u8; 4]
+}
+
+struct AnnoyodoroTray {
+    state: TrayState,
+    events: Sender<TrayEvent>
+}
+
+fn color([r, g, b, a]: [u8; 4]) -> Color {
+    Color::from_rgba8(r, g, b, f32::from(a) / 255.0)
+}
+
+impl AnnoyodoroTray {
+    fn send(&mut self, action: TrayAction) {
+        // The main window may be busy, e.g. during a break
+        let _ = self.events.try_send(TrayEvent::Action(action));
+    }
+
+    fn description(&self) -> String {
+        let TrayState {
+            remaining_secs,
+            on_break,
+            paused,
+            last_work_session,
+            ..
+        } = self.state;
+
+        let Some(secs) = remaining_secs else {
+            return "Waiting for the goal of the first work session".to_string();
+        };
+
+        if on_break {
+            return match secs {
+                0 => "The break is over, waiting for the next work goal".to_string(),
+                secs => format!("{}:{:02} left of the break", secs / 60, secs % 60)
+            };
+        }
+
+        let mut description = format!("{}:{:02} until the next break", secs / 60, secs % 60);
+        if paused {
+            description.insert_str(0, "Paused, ");
+        }
+
+        if last_work_session {
+            description.push_str(" (last work session)");
+        }
+
+        description
+    }
+
+    fn ring_icon(&self) -> Icon {
+        let center = ICON_SIZE as f32 / 2.0;
+        let (outer_radius, inner_radius) = (center - 1.0, center - 6.0);
+        let progress = f32::from(self.state.progress) / f32::from(ICON_STEPS);
+        let accent = if self.state.paused {
+            color(self.state.accent).scale_alpha(0.5)
+        } else {
+            color(self.state.accent)
+        };
+        let track = color(self.state.track);
+
+        let data = (0..ICON_SIZE * ICON_SIZE)
+            .flat_map(|i| {
+                let x = (i % ICON_SIZE) as f32 + 0.5 - center;
+                let y = (i / ICON_SIZE) as f32 + 0.5 - center;
+                let distance = x.hypot(y);
+                if !(inner_radius..=outer_radius).contains(&distance) {
+                    return [0; 4];
+                }
+
+                // Clockwise from the top like the ring in the main window
+                let angle = x.atan2(-y).rem_euclid(PI * 2.0);
+                let color = if angle / (PI * 2.0) <= progress {
+                    accent
+                } else {
+                    track
+                };
+
+                let [r, g, b, a] = color.into_rgba8();
+                [a, r, g, b]
+            })
+            .collect();
+
+        Icon {
+            width: ICON_SIZE,
+            height: ICON_SIZE,
+            data
+        }
+    }
+}
+
+impl Tray for AnnoyodoroTray {
+    fn id(&self) -> String {
+        "annoyodoro".to_string()
+    }
+
+    fn title(&self) -> String {
+        "Annoyodoro".to_string()
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayAction::ShowWindow);
+    }
+
+    fn icon_pixmap(&self) -> Vec<Icon> {
+        vec![self.ring_icon()]
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: "Annoyodoro".to_string(),
+            description: self.description(),
+            ..Default::default()
+        }
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        let running = self.state.remaining_secs.is_some();
+        let item = |label: &str, enabled: bool, action: TrayAction| {
+            StandardItem {
+                label: label.to_string(),
+                enabled,
+                activate: Box::new(move |tray: &mut Self| tray.send(action)),
+                ..Default::default()
+            }
+            .into()
+        };
+
+        vec![
+            item(
+                if self.state.paused { "Resume" } else { "Pause" },
+                running,
+                TrayAction::TogglePause
+            ),
+            CheckmarkItem {
+                label: "Last work session".to_string(),
+                enabled: running,
+                checked: self.state.last_work_session,
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::ToggleLastWorkSession)),
+                ..Default::default()
+            }
+            .into(),
+            item("Break now", running, TrayAction::BreakNow),
+            MenuItem::Separator,
+            item("Stats", true, TrayAction::OpenStats),
+            item("Show window", true, TrayAction::ShowWindow),
+            MenuItem::Separator,
+            item("Quit", true, TrayAction::Quit),
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct TrayHandle {
+    handle: Handle<AnnoyodoroTray>,
+    shown: TrayState
+}
+
+impl Debug for TrayHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrayHandle")
+            .field("shown", &self.shown)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TrayHandle {
+    /// Only touches D-Bus if `state` differs from what's shown
+    pub fn update<T: Send + 'static>(&mut self, state: TrayState) -> Task<T> {
+        if self.shown == state {
+            return Task::none();
+        }
+
+        self.shown = state;
+        let handle = self.handle.clone();
+        Task::future(async move { handle.update(|tray| tray.state = state).await }).discard()
+    }
+}
+
+/// Spawns the tray, if there's no StatusNotifierItem host the stream just never produces anything
+pub fn run() -> impl Stream<Item = TrayEvent> {
+    stream::channel(10, async |mut events: Sender<TrayEvent>| {
+        let tray = AnnoyodoroTray {
+            state: TrayState::default(),
+            events: events.clone()
+        };
+
+        match tray.spawn().await {
+            Ok(handle) => {
+                let handle = TrayHandle {
+                    handle,
+                    shown: TrayState::default()
+                };
+                let _ = events.send(TrayEvent::Ready(handle)).await;
+            }
+            Err(err) => eprintln!("Cannot show tray icon: {err}")
+        }
+
+        future::pending::<()>().await
+    })
+}
@@ -0,0 +1,447 @@
+//! `annoyodoro tui`, the timer in a terminal for when there's no GPU or Wayland session at hand
+use std::{iter, mem, ops::ControlFlow, time::Duration};
+
+use iced::Theme;
+use itertools::Itertools;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, LineGauge, Paragraph, Row, Table}
+};
+use yanet::Result;
+
+use crate::{
+    clock::SystemClock,
+    config::Config,
+    keybindings::Action,
+    session::{Effect, Session, SessionEvent, SessionPhase},
+    stats::StatsManager,
+    work_timer::WorkTimer
+};
+
+/// How long to wait for input while nothing counts down
+const IDLE: Duration = Duration::from_secs(60);
+const WIDTH: u16 = 60;
+
+const BIG_DIGITS: [[&str; 5]; 10] = [
+    ["███", "█ █", "█ █", "█ █", "███"],
+    [" █ ", "██ ", " █ ", " █ ", "███"],
+    ["███", "  █", "███", "█  ", "███"],
+    ["███", "  █", "███", "  █", "███"],
+    ["█ █", "█ █", "███", "  █", "  █"],
+    ["███", "█  ", "███", "  █", "███"],
+    ["███", "█  ", "███", "█ █", "███"],
+    ["███", "  █", "  █", "  █", "  █"],
+    ["███", "█ █", "███", "█ █", "███"],
+    ["███", "█ █", "███", "  █", "███"]
+];
+const BIG_COLON: [&str; 5] = [" ", "█", " ", "█", " "];
+
+pub fn run(config: Config) -> Result<()> {
+    let tui = Tui {
+        config,
+        theme: config.theme(),
+        stats: StatsManager::load()?,
+        session: Session::new(config.pomodoro),
+        break_timer: WorkTimer::new(Duration::ZERO, &SystemClock),
+        goal: String::new(),
+        showing_stats: false,
+        error: None
+    };
+
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+struct Tui {
+    config: Config,
+    theme: Theme,
+    stats: StatsManager,
+    session: Session,
+    /// Counts the break down, only meaningful during one
+    break_timer: WorkTimer,
+    /// The goal being typed
+    goal: String,
+    showing_stats: bool,
+    error: Option<String>
+}
+
+fn color(color: iced::Color) -> Color {
+    let [r, g, b, _] = color.into_rgba8();
+    Color::Rgb(r, g, b)
+}
+
+impl Tui {
+    fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(self.until_next_tick())?
+                && let Event::Key(key) = event::read()?
+                && self.on_key(key).is_break()
+            {
+                return Ok(());
+            }
+
+            if self.on_tick().is_break() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Only wakes up when the countdown shown changes, the gauges are too coarse to move in between
+    fn until_next_tick(&self) -> Duration {
+        let timer = match self.session.phase() {
+            SessionPhase::Work { work_timer, .. } => work_timer,
+            SessionPhase::Break { .. } => self.break_timer,
+            SessionPhase::WaitingForGoal => return IDLE
+        };
+
+        if timer.is_paused() || timer.duration_remaning().is_zero() {
+            return IDLE;
+        }
+
+        timer.until_next_second()
+    }
+
+    fn on_tick(&mut self) -> ControlFlow<()> {
+        if let SessionPhase::Break { .. } = self.session.phase() {
+            self.break_timer.on_tick(&SystemClock);
+        }
+
+        self.session_event(SessionEvent::Tick)
+    }
+
+    /// Only the cycle itself is carried out, there are no notifications, hooks or sounds
+    fn session_event(&mut self, event: SessionEvent) -> ControlFlow<()> {
+        for effect in self.session.handle(event, &SystemClock) {
+            match effect {
+                Effect::BreakStart { long_break } => {
+                    self.break_timer = WorkTimer::new(
+                        self.config.pomodoro.break_duration(long_break),
+                        &SystemClock
+                    )
+                }
+                Effect::WorkStart { goal, first } => {
+                    if let Err(err) = self.start_work(goal, first) {
+                        self.error = Some(err.to_string());
+                    }
+                }
+                Effect::SessionEnd => return ControlFlow::Break(()),
+                Effect::PreBreakNotification { .. }
+                | Effect::Paused
+                | Effect::Resumed
+                | Effect::BreakEnd { .. }
+                | Effect::LastWorkSessionOver => {}
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn start_work(&mut self, goal: String, first: bool) -> Result<()> {
+        if first {
+            self.stats.increment_app_sessions_count();
+        } else {
+            self.stats.increment_pomodori_count();
+        }
+
+        self.stats.add_work_goal(goal);
+        self.stats.save()?;
+        self.stats.reload_if_needed()?;
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> ControlFlow<()> {
+        let typing =
+            !matches!(self.session.phase(), SessionPhase::Work { .. }) && !self.showing_stats;
+        if let Some(action) = self.config.keybindings.terminal_action(key, typing) {
+            return self.on_action(action);
+        }
+
+        if !typing {
+            return ControlFlow::Continue(());
+        }
+
+        match key.code {
+            KeyCode::Char(char)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.goal.push(char)
+            }
+            KeyCode::Backspace => _ = self.goal.pop(),
+            KeyCode::Enter => return self.submit_goal(),
+            _ => {}
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn on_action(&mut self, action: Action) -> ControlFlow<()> {
+        match action {
+            Action::Quit => self.session_event(SessionEvent::Quit),
+            Action::OpenStats => {
+                self.showing_stats = !self.showing_stats;
+                ControlFlow::Continue(())
+            }
+            Action::Pause => self.session_event(SessionEvent::TogglePause),
+            Action::LastSession => self.session_event(SessionEvent::ToggleLastWorkSession),
+            Action::BreakNow => self.session_event(SessionEvent::BreakNow),
+            // The goal input is always focused and there's no break overlay
+            Action::FocusGoal
+            | Action::FocusNext
+            | Action::FocusPrevious
+            | Action::NextSuggestion
+            | Action::SuggestionDone
+            | Action::ToggleBreathing
+            | Action::DismissMicroBreak => ControlFlow::Continue(())
+        }
+    }
+
+    fn submit_goal(&mut self) -> ControlFlow<()> {
+        let submittable = match self.session.phase() {
+            SessionPhase::WaitingForGoal => true,
+            SessionPhase::Break { .. } => self.break_timer.duration_remaning().is_zero(),
+            SessionPhase::Work { .. } => false
+        };
+        if self.goal.trim().is_empty() || !submittable {
+            return ControlFlow::Continue(());
+        }
+
+        let goal = mem::take(&mut self.goal);
+        self.session_event(SessionEvent::GoalSubmitted(goal))
+    }
+
+    fn big_time(&self, duration: Duration, accent: Color) -> Text<'static> {
+        let secs = duration.as_secs();
+        let glyphs = format!("{}:{:02}", secs / 60, secs % 60);
+        let separator = color(self.theme.extended_palette().background.strongest.color);
+        let lines = (0..5).map(|row| {
+            glyphs
+                .chars()
+                .map(|glyph| match glyph.to_digit(10) {
+                    Some(digit) => Span::from(BIG_DIGITS[digit as usize][row]).fg(accent),
+                    None => Span::from(BIG_COLON[row]).fg(separator)
+                })
+                .interleave_shortest(iter::repeat_n(Span::from(" "), glyphs.len() - 1))
+                .collect::<Line>()
+        });
+        Text::from_iter(lines).centered()
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [area] = Layout::horizontal([Constraint::Length(WIDTH)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        if self.showing_stats {
+            return self.draw_stats(frame, area);
+        }
+
+        match self.session.phase() {
+            SessionPhase::WaitingForGoal => self.draw_prompt(
+                frame,
+                area,
+                Line::from("Enter the goal of your first work session")
+            ),
+            SessionPhase::Work { work_timer, .. } => self.draw_work(frame, area, work_timer),
+            SessionPhase::Break { long_break } => self.draw_break(frame, area, long_break)
+        }
+    }
+
+    fn progress(timer: WorkTimer, duration: Duration) -> f64 {
+        // Snoozing can make the remaining duration longer than the duration
+        (1.0 - timer.duration_remaning().as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0)
+    }
+
+    fn gauge(&self, ratio: f64, accent: Color) -> LineGauge<'static> {
+        LineGauge::default()
+            .ratio(ratio)
+            .label("")
+            .filled_style(Style::new().fg(accent))
+            .unfilled_style(
+                Style::new().fg(color(self.theme.extended_palette().background.weak.color))
+            )
+    }
+
+    fn help(&self) -> Paragraph<'static> {
+        let help = self
+            .config
+            .keybindings
+            .bound()
+            .filter(|(action, _)| action.in_terminal())
+            .map(|(action, chord)| format!("{chord} {}", action.to_string().replace('_', " ")))
+            .join(" · ");
+        Paragraph::new(help)
+            .centered()
+            .fg(color(self.theme.extended_palette().background.strong.color))
+    }
+
+    fn draw_work(&self, frame: &mut Frame, area: Rect, work_timer: WorkTimer) {
+        let palette = self.theme.palette();
+        let accent = color(palette.primary);
+        let [title, timer, gauge, _, info, error, _, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(4),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(2)
+        ])
+        .flex(Flex::Center)
+        .areas(area);
+
+        if work_timer.is_paused() {
+            frame.render_widget(Line::from("Paused").fg(accent).centered(), title);
+        }
+
+        frame.render_widget(self.big_time(work_timer.duration_remaning(), accent), timer);
+        frame.render_widget(
+            self.gauge(
+                Self::progress(work_timer, self.config.pomodoro.work_duration),
+                accent
+            ),
+            gauge
+        );
+
+        let checkbox = if self.session.last_work_session() {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        let goal = self
+            .stats
+            .work_goals()
+            .last()
+            .map(|(_, goal)| goal.as_str())
+            .unwrap_or_default();
+        let rows = [
+            Row::new([format!("{checkbox} Last work session"), String::new()]),
+            Row::new([
+                "Next long break in".to_string(),
+                format!("{} pomodori", self.session.long_break_in())
+            ]),
+            Row::new([
+                "Pomodori today".to_string(),
+                self.stats.pomodori_daily().to_string()
+            ]),
+            Row::new(["Current work goal".to_string(), goal.to_string()])
+        ];
+        frame.render_widget(
+            Table::new(rows, [Constraint::Fill(1), Constraint::Fill(1)]),
+            info
+        );
+
+        if let Some(err) = &self.error {
+            frame.render_widget(Line::from(err.as_str()).fg(color(palette.danger)), error);
+        }
+
+        frame.render_widget(self.help(), help);
+    }
+
+    fn draw_break(&self, frame: &mut Frame, area: Rect, long_break: bool) {
+        let timer = self.break_timer;
+        let duration = self.config.pomodoro.break_duration(long_break);
+        let palette = self.theme.palette();
+        let over = timer.duration_remaning().is_zero();
+        let (title_text, accent) = if over {
+            ("Time to work! (submit your work goal)", palette.danger)
+        } else if long_break {
+            ("Time for a long break", palette.primary)
+        } else {
+            ("Time for a break!", palette.primary)
+        };
+
+        let accent = color(accent);
+        let [title, _, time, gauge, prompt] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Length(6)
+        ])
+        .flex(Flex::Center)
+        .areas(area);
+        frame.render_widget(Line::from(title_text).bold().centered(), title);
+        frame.render_widget(self.big_time(timer.duration_remaning(), accent), time);
+        frame.render_widget(self.gauge(Self::progress(timer, duration), accent), gauge);
+        self.draw_prompt(
+            frame,
+            prompt,
+            Line::from("Enter the goal of your next work session")
+        );
+    }
+
+    fn draw_prompt(&self, frame: &mut Frame, area: Rect, label: Line) {
+        let [label_area, input, error, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(1)
+        ])
+        .flex(Flex::Center)
+        .areas(area);
+        frame.render_widget(label.centered(), label_area);
+        frame.render_widget(
+            Paragraph::new(self.goal.as_str()).block(Block::bordered().title("Work goal")),
+            input
+        );
+        // Within the borders, as far as a tiny terminal allows
+        let typed = u16::try_from(self.goal.chars().count()).unwrap_or(u16::MAX);
+        let cursor = (input.x.saturating_add(1).saturating_add(typed))
+            .min(input.right().saturating_sub(2))
+            .max(input.x);
+        frame.set_cursor_position(Position::new(cursor, input.y + 1));
+
+        if let Some(err) = &self.error {
+            frame.render_widget(
+                Line::from(err.as_str()).fg(color(self.theme.palette().danger)),
+                error
+            );
+        }
+
+        frame.render_widget(self.help(), help);
+    }
+
+    fn draw_stats(&self, frame: &mut Frame, area: Rect) {
+        let accent = color(self.theme.palette().primary);
+        let rows = self
+            .stats
+            .counts()
+            .into_iter()
+            .zip(self.stats.suggestions_done())
+            .map(|((label, count), suggestions_done)| {
+                Row::new([
+                    Span::from(label),
+                    Span::from(count.pomodori.to_string()).fg(accent),
+                    Span::from(count.sessions.to_string()).fg(accent),
+                    Span::from(suggestions_done.to_string()).fg(accent)
+                ])
+            });
+        let [table, help] = Layout::vertical([Constraint::Length(8), Constraint::Length(2)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Fill(2),
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Fill(1)
+                ]
+            )
+            .header(Row::new(["", "Pomodori", "Sessions", "Suggestions"]).bold())
+            .block(Block::bordered().title("Stats")),
+            table
+        );
+        frame.render_widget(self.help(), help);
+    }
+}
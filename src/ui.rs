@@ -0,0 +1,283 @@
+//! `annoyodoro ui`, a main window attached to the running daemon over D-Bus
+use std::{cell::RefCell, mem, time::Duration};
+
+use iced::{
+    Element, Length, Subscription, Task, exit, keyboard, time,
+    widget::{
+        self, container,
+        operation::{focus, focus_next, focus_previous},
+        text
+    }
+};
+use lucide_icons::LUCIDE_FONT_BYTES;
+use yanet::Result;
+
+use crate::{
+    Message,
+    clock::SystemClock,
+    color_scheme,
+    config::Config,
+    dbus::{self, Phase, RemoteEvent, TimerProxy, TimerState},
+    stats::StatsManager,
+    view::{self, TimerView},
+    work_timer::WorkTimer
+};
+
+pub fn run(config: Config) -> Result<()> {
+    let default_font = config.font;
+    let once_boot = RefCell::new(Some(Ui {
+        config,
+        stats: StatsManager::load()?,
+        timer: None,
+        remote: None,
+        work_timer: WorkTimer::mirror(Duration::ZERO, true, &SystemClock),
+        goal: String::new(),
+        showing_stats: false,
+        error: None
+    }));
+    let boot = move || once_boot.borrow_mut().take().unwrap();
+
+    iced::application(boot, Ui::update, Ui::view)
+        .title("Annoyodoro")
+        .subscription(Ui::subscription)
+        .default_font(default_font)
+        .font(LUCIDE_FONT_BYTES)
+        .theme(|ui: &Ui| ui.config.theme())
+        .run()?;
+
+    Ok(())
+}
+
+struct Ui {
+    config: Config,
+    stats: StatsManager,
+    /// `None` until attached to the daemon
+    timer: Option<TimerProxy<'static>>,
+    remote: Option<TimerState>,
+    /// Ticks locally between the updates from the daemon
+    work_timer: WorkTimer,
+    goal: String,
+    showing_stats: bool,
+    error: Option<String>
+}
+
+#[derive(Debug, Clone)]
+enum UiMessage {
+    View(Message),
+    Remote(RemoteEvent),
+    Failed(String)
+}
+
+impl Ui {
+    /// Calls a method of the daemon, its failure is shown in the window
+    fn call<F>(&self, call: impl FnOnce(TimerProxy<'static>) -> F) -> Task<UiMessage>
+    where
+        F: Future<Output = zbus::Result<()>> + Send + 'static
+    {
+        let Some(timer) = self.timer.clone() else {
+            return Task::none();
+        };
+
+        let call = call(timer);
+        Task::future(async move { call.await.err() })
+            .and_then(|err| Task::done(UiMessage::Failed(err.to_string())))
+    }
+
+    fn update(&mut self, message: UiMessage) -> Task<UiMessage> {
+        self.try_update(message).unwrap_or_else(|err| {
+            self.error = Some(err.to_string());
+            Task::none()
+        })
+    }
+
+    fn try_update(&mut self, message: UiMessage) -> Result<Task<UiMessage>> {
+        let message = match message {
+            UiMessage::View(message) => message,
+            UiMessage::Remote(RemoteEvent::Attached(timer)) => {
+                self.timer = Some(timer);
+                return Ok(Task::none());
+            }
+            UiMessage::Remote(RemoteEvent::State(state)) => {
+                let was_prompting = self
+                    .remote
+                    .as_ref()
+                    .is_some_and(|remote| remote.phase == Phase::WaitingForGoal);
+                let prompting = state.phase == Phase::WaitingForGoal;
+                self.work_timer = WorkTimer::mirror(
+                    Duration::from_secs(state.remaining_secs),
+                    state.paused,
+                    &SystemClock
+                );
+                self.remote = Some(state);
+                if prompting && !was_prompting {
+                    return Ok(focus("work-goal"));
+                }
+
+                return Ok(Task::none());
+            }
+            UiMessage::Remote(RemoteEvent::Detached(err)) => {
+                self.timer = None;
+                self.remote = None;
+                self.error = Some(format!("Not attached to annoyodoro daemon: {err}"));
+                return Ok(Task::none());
+            }
+            UiMessage::Failed(err) => {
+                self.error = Some(err);
+                return Ok(Task::none());
+            }
+        };
+
+        let paused = self.work_timer.is_paused();
+        let last_session = self
+            .remote
+            .as_ref()
+            .is_some_and(|remote| remote.last_session);
+        let task = match message {
+            Message::InitialWorkGoalChange(goal) => {
+                self.goal = goal;
+                Task::none()
+            }
+            Message::InitialWorkGoalSubmit => {
+                let goal = mem::take(&mut self.goal);
+                self.call(|timer| async move { timer.submit_goal(&goal).await })
+            }
+            Message::TogglePause if paused => {
+                self.call(|timer| async move { timer.resume().await })
+            }
+            Message::TogglePause => self.call(|timer| async move { timer.pause().await }),
+            Message::ToggleLastWorkSession => {
+                self.call(move |timer| async move { timer.set_last_session(!last_session).await })
+            }
+            Message::BreakNow => self.call(|timer| async move { timer.start_break().await }),
+            Message::ToggleStats => {
+                self.showing_stats = !self.showing_stats;
+                if self.showing_stats {
+                    // The daemon owns the stats, they may have changed since they were loaded
+                    self.stats = StatsManager::load()?;
+                }
+
+                Task::none()
+            }
+            Message::FocusGoal => focus("work-goal"),
+            Message::FocusNext => focus_next(),
+            Message::FocusPrevious => focus_previous(),
+            // Only detaches, the daemon keeps running
+            Message::Quit => exit(),
+            Message::Tick => {
+                self.work_timer.on_tick(&SystemClock);
+                Task::none()
+            }
+            Message::ColorScheme(color_scheme) => {
+                self.config.set_color_scheme(color_scheme);
+                Task::none()
+            }
+            Message::NotificationAction(..)
+            | Message::Tray(_)
+            | Message::Dbus(_)
+            | Message::Break(_)
+            | Message::DismissMicroBreak
+            | Message::WindowClosed(_)
+            | Message::WindowResized(..)
+            | Message::AnchorChange { .. }
+            | Message::SetInputRegion { .. }
+            | Message::AnchorSizeChange { .. }
+            | Message::LayerChange { .. }
+            | Message::MarginChange { .. }
+            | Message::SizeChange { .. }
+            | Message::ExclusiveZoneChange { .. }
+            | Message::KeyboardInteractivityChange { .. }
+            | Message::VirtualKeyboardPressed { .. }
+            | Message::NewLayerShell { .. }
+            | Message::NewBaseWindow { .. }
+            | Message::NewPopUp { .. }
+            | Message::NewMenu { .. }
+            | Message::NewInputPanel { .. }
+            | Message::RemoveWindow(_)
+            | Message::ForgetLastOutput => Task::none()
+        };
+
+        Ok(task)
+    }
+
+    fn view(&self) -> Element<'_, UiMessage> {
+        let metrics = self.config.layout.metrics(None);
+        if self.showing_stats {
+            return view::stats_view(&self.config, metrics, &self.stats).map(UiMessage::View);
+        }
+
+        let Some(remote) = &self.remote else {
+            let status = self
+                .error
+                .as_deref()
+                .unwrap_or("Attaching to annoyodoro daemon");
+            let style = if self.error.is_some() {
+                text::danger
+            } else {
+                text::default
+            };
+            return container(text(status).style(style))
+                .padding(metrics.spacing)
+                .center(Length::Fill)
+                .into();
+        };
+
+        let view = match remote.phase {
+            Phase::WaitingForGoal => view::initial_work_goal_prompt(metrics, &self.goal),
+            Phase::Work => view::main_view(
+                &self.config,
+                metrics,
+                TimerView {
+                    long_break_in: remote.long_break_in,
+                    work_timer: self.work_timer,
+                    last_work_session: remote.last_session,
+                    pomodori_today: remote.pomodori_today,
+                    goal: &remote.current_goal,
+                    enforcement: None
+                },
+                self.error.as_deref()
+            ),
+            Phase::Break => container(widget::text("Enjoy your break"))
+                .center(Length::Fill)
+                .into()
+        };
+
+        view.map(UiMessage::View)
+    }
+
+    fn subscription(&self) -> Subscription<UiMessage> {
+        let counting_down = self
+            .remote
+            .as_ref()
+            .is_some_and(|remote| remote.phase == Phase::Work && !remote.paused);
+        let tick = if counting_down {
+            let interval = if self.config.accessibility.reduced_motion {
+                Duration::from_secs(1)
+            } else {
+                self.config.ring_update_interval
+            };
+            time::every(interval).map(|_| UiMessage::View(Message::Tick))
+        } else {
+            Subscription::none()
+        };
+
+        let color_scheme = if self.config.follows_color_scheme() {
+            Subscription::run(color_scheme::run)
+                .map(|scheme| UiMessage::View(Message::ColorScheme(scheme)))
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([
+            tick,
+            color_scheme,
+            Subscription::run(dbus::attach).map(UiMessage::Remote),
+            keyboard::listen()
+                .with(self.config.keybindings)
+                .filter_map(|(keybindings, event)| {
+                    keybindings
+                        .action(event)
+                        .map(|action| UiMessage::View(Message::from(action)))
+                })
+        ])
+    }
+}
@@ -1,141 +1,316 @@
 use iced::{
-    Alignment, Element, Font, Length, never, padding,
+    Alignment, Color, ContentFit, Element, Font, Length, never, padding,
     widget::{
         self, Container, button, column, container, rich_text, row, rule, span, stack,
-        text::{self, Wrapping}
-    }
+        text::{self, Wrapping},
+        tooltip
+    },
+    window
 };
 use jiff::SignedDuration;
 use lucide_icons::Icon;
 
-use crate::{Annoyodoro, AppState, Message, circular::Circular, work_timer::WorkTimer};
+use crate::{
+    Annoyodoro, Message, circular::Circular, config::Config, enforcement::Enforcement,
+    keybindings::Action, micro_breaks::MicroBreaks, session::SessionPhase, stats::StatsManager,
+    work_timer::WorkTimer
+};
 
-pub const SPACING: f32 = 5.0;
-pub const TIMER_TEXT_SIZE: f32 = 110.0;
-pub const BIG_TEXT: f32 = 18.0;
+/// Sizes from the `[layout]` config, scaled to the output
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub scale: f32,
+    pub ring_size: f32,
+    pub ring_width: f32,
+    pub timer_text_size: f32,
+    pub text_size: f32,
+    pub spacing: f32
+}
+
+/// What the main window shows while the timer is running, also drawn by `annoyodoro ui`
+#[derive(Debug, Clone, Copy)]
+pub struct TimerView<'a> {
+    pub long_break_in: u16,
+    pub work_timer: WorkTimer,
+    pub last_work_session: bool,
+    pub pomodori_today: u32,
+    pub goal: &'a str,
+    /// `None` if unknown, e.g. in `annoyodoro ui`
+    pub enforcement: Option<Enforcement>
+}
 
 impl Annoyodoro {
-    pub fn view(&self) -> Element<'_, Message> {
-        match self.state {
-            AppState::Running { work_timer, .. } if work_timer.duration_remaning().is_zero() => {
-                "If you're seeing this, the break timer didn't spawn and it's a bug".into()
-            }
-            AppState::Running {
-                long_break_in,
-                work_timer,
-                last_work_session,
-                ..
-            } => self.main_view(long_break_in, work_timer, last_work_session),
-            AppState::InitialWorkGoalPrompt { ref goal } => Self::initial_work_goal_prompt(goal)
+    /// The background of every surface is drawn here, so that the overlays can be see-through
+    pub fn view(&self, window: window::Id) -> Element<'_, Message> {
+        let layout = self.config.layout;
+        let break_overlay = self
+            .on_break
+            .as_ref()
+            .is_some_and(|on_break| on_break.window == window);
+        let overlay = break_overlay || self.micro_break_window == Some(window);
+        let opacity = if overlay { layout.overlay_opacity } else { 1.0 };
+        // Only the break overlay covers its output, the other surfaces keep their size
+        let metrics = layout.metrics(self.output_size.filter(|_| break_overlay));
+        let background = self.config.theme().palette().background;
+        let content = container(self.surface_view(window, metrics)).style(move |_| {
+            container::background(Color {
+                a: background.a * opacity,
+                ..background
+            })
+        });
+
+        match layout.overlay_background {
+            Some(path) if overlay => stack![
+                widget::image(path)
+                    .content_fit(ContentFit::Cover)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+                content
+            ]
+            .into(),
+            _ => content.into()
         }
     }
 
-    fn main_view(
-        &self,
-        long_break_in: u16,
-        work_timer: WorkTimer,
-        last_work_session: bool
-    ) -> Element<'_, Message> {
-        let palette = self.config.theme().palette();
-        let time_left = work_timer
-            .duration_remaning()
-            .try_into()
-            .unwrap_or(SignedDuration::MAX);
-        let time_left = rich_text![
-            span(time_left.as_mins().to_string()).color(palette.primary),
-            span(":").color(
-                self.config
-                    .theme()
-                    .extended_palette()
-                    .background
-                    .strongest
-                    .color
+    fn surface_view(&self, window: window::Id, metrics: Metrics) -> Element<'_, Message> {
+        if self.micro_break_window == Some(window) {
+            return micro_break_view(&self.config, metrics, &self.micro_breaks);
+        }
+
+        if let Some(on_break) = &self.on_break {
+            if on_break.window == window {
+                return on_break.timer.view(metrics).map(Message::Break);
+            }
+
+            return container("Enjoy your break").center(Length::Fill).into();
+        }
+
+        if self.showing_stats {
+            return stats_view(&self.config, metrics, &self.stats);
+        }
+
+        match self.session.phase() {
+            SessionPhase::Work { work_timer, .. } => main_view(
+                &self.config,
+                metrics,
+                TimerView {
+                    long_break_in: self.session.long_break_in(),
+                    work_timer,
+                    last_work_session: self.session.last_work_session(),
+                    pomodori_today: self.stats.pomodori_daily(),
+                    goal: &self.stats.work_goals().last().unwrap().1,
+                    enforcement: Some(self.enforcement)
+                },
+                self.error.as_deref()
             ),
-            span(format!("{:02}", time_left.as_secs().abs() % 60)).color(palette.primary)
-        ]
-        .on_link_click(never)
-        .size(TIMER_TEXT_SIZE);
-        let toggle_pause_button_icon = if work_timer.is_paused() {
-            Icon::Play
-        } else {
-            Icon::Pause
-        };
-        let toggle_pause_button = button(
+            SessionPhase::Break { .. } => container("Enjoy your break").center(Length::Fill).into(),
+            SessionPhase::WaitingForGoal => initial_work_goal_prompt(metrics, &self.goal)
+        }
+    }
+}
+
+pub fn main_view<'a>(
+    config: &Config,
+    metrics: Metrics,
+    TimerView {
+        long_break_in,
+        work_timer,
+        last_work_session,
+        pomodori_today,
+        goal,
+        enforcement
+    }: TimerView<'a>,
+    error: Option<&'a str>
+) -> Element<'a, Message> {
+    let palette = config.theme().palette();
+    let ring_colors = config.ring_colors();
+    let ring_color = if work_timer.is_paused() {
+        ring_colors.paused
+    } else {
+        ring_colors.work
+    };
+    let time_left = work_timer
+        .duration_remaning()
+        .try_into()
+        .unwrap_or(SignedDuration::MAX);
+    let time_left = rich_text![
+        span(time_left.as_mins().to_string()).color(ring_color),
+        span(":").color(config.theme().extended_palette().background.strongest.color),
+        span(format!("{:02}", time_left.as_secs().abs() % 60)).color(ring_color)
+    ]
+    .on_link_click(never)
+    .size(metrics.timer_text_size);
+    let (toggle_pause_button_icon, toggle_pause_label) = if work_timer.is_paused() {
+        (Icon::Play, "Resume")
+    } else {
+        (Icon::Pause, "Pause")
+    };
+    // The glyph alone doesn't say what the button does
+    let toggle_pause_button = tooltip(
+        button(
             widget::text(toggle_pause_button_icon.unicode())
                 .font(Font::with_name("lucide"))
-                .size(BIG_TEXT)
+                .size(metrics.text_size)
         )
-        .on_press(Message::TogglePause);
-
-        let timer = stack![
-            Circular {
-                percentage: 1.0
-                    - work_timer.duration_remaning().as_millis() as f32
-                        / self.config.pomodoro.work_duration.as_millis() as f32,
-                color: palette.primary,
-                theme: self.config.theme()
-            },
-            container(time_left).center(Length::Fill),
-            container(toggle_pause_button)
-                .align_right(Length::Fill)
-                .center_y(Length::Fill)
-                .padding(padding::right(SPACING * 5.0)),
-        ];
-        let column = column![
-            widget::checkbox(last_work_session)
-                .on_toggle(|_| Message::ToggleLastWorkSession)
-                .label("Last work session")
-                .text_size(BIG_TEXT)
-                .size(BIG_TEXT),
-            rule::horizontal(2.0),
-            row![
-                container("Next long break in").width(Length::Fill),
-                container(widget::text(format!("{long_break_in} pomodori")).color(palette.primary))
-                    .align_right(Length::Fill)
-            ],
-            row![
-                container("Pomodori today").width(Length::Fill),
-                container(
-                    widget::text(self.stats.pomodori_daily().to_string()).color(palette.primary)
-                )
+        .on_press(Message::TogglePause),
+        container(widget::text(
+            config.keybindings.label(toggle_pause_label, Action::Pause)
+        ))
+        .padding(metrics.spacing)
+        .style(container::rounded_box),
+        tooltip::Position::Left
+    );
+
+    let timer = stack![
+        Circular {
+            // Snoozing can make the remaining duration longer than the work duration
+            percentage: (1.0
+                - work_timer.duration_remaning().as_millis() as f32
+                    / config.pomodoro.work_duration.as_millis() as f32)
+                .max(0.0),
+            color: ring_color,
+            track: ring_colors.track,
+            theme: config.theme(),
+            size: 1.0,
+            diameter: metrics.ring_size,
+            stroke_width: metrics.ring_width
+        },
+        container(time_left).center(Length::Fill),
+        container(toggle_pause_button)
+            .align_right(Length::Fill)
+            .center_y(Length::Fill)
+            .padding(padding::right(metrics.spacing * 5.0)),
+    ];
+    let column = column![
+        widget::checkbox(last_work_session)
+            .on_toggle(|_| Message::ToggleLastWorkSession)
+            .label(
+                config
+                    .keybindings
+                    .label("Last work session", Action::LastSession)
+            )
+            .text_size(metrics.text_size)
+            .size(metrics.text_size),
+        rule::horizontal(2.0),
+        row![
+            container("Next long break in").width(Length::Fill),
+            container(widget::text(format!("{long_break_in} pomodori")).color(palette.primary))
                 .align_right(Length::Fill)
-            ],
-            row![
-                "Current work goal",
-                container(
-                    widget::text(&self.stats.work_goals().last().unwrap().1)
-                        .wrapping(Wrapping::WordOrGlyph)
-                )
+        ],
+        row![
+            container("Pomodori today").width(Length::Fill),
+            container(widget::text(pomodori_today.to_string()).color(palette.primary))
                 .align_right(Length::Fill)
-            ],
-            self.error
-                .as_ref()
-                .map(|e| widget::text(e).style(text::danger))
-        ]
-        .spacing(SPACING)
-        .width(TIMER_TEXT_SIZE * 4.0);
-        #[cfg(debug_assertions)]
-        let column = column.push(
-            button("Early break (enabled only in debug mode)").on_press(Message::DebugEarlyBreak)
-        );
-
-        let content = row![timer, column]
-            .spacing(SPACING * 2.0)
-            .align_y(Alignment::Center);
-        Container::new(content).center(Length::Fill).into()
-    }
+        ],
+        row![
+            "Current work goal",
+            container(widget::text(goal).wrapping(Wrapping::WordOrGlyph)).align_right(Length::Fill)
+        ],
+        enforcement.map(|enforcement| row![
+            container("Breaks shown in").width(Length::Fill),
+            container(widget::text(enforcement.to_string())).align_right(Length::Fill)
+        ]),
+        error.map(|e| widget::text(e).style(text::danger))
+    ]
+    .spacing(metrics.spacing)
+    .width(metrics.timer_text_size * 4.0);
+    #[cfg(debug_assertions)]
+    let column =
+        column.push(button("Early break (enabled only in debug mode)").on_press(Message::BreakNow));
+
+    let content = row![timer, column]
+        .spacing(metrics.spacing * 2.0)
+        .align_y(Alignment::Center);
+    Container::new(content).center(Length::Fill).into()
+}
+
+fn micro_break_view<'a>(
+    config: &Config,
+    metrics: Metrics,
+    micro_breaks: &MicroBreaks
+) -> Element<'a, Message> {
+    let remaining = micro_breaks
+        .current()
+        .map_or(0, |micro_break| micro_break.duration_remaning().as_secs());
+    let dismiss = micro_breaks.dismissable().then(|| {
+        button(widget::text(
+            config
+                .keybindings
+                .label("Dismiss", Action::DismissMicroBreak)
+        ))
+        .on_press(Message::DismissMicroBreak)
+    });
+    let column = column![
+        widget::text("Look at something far away")
+            .size(metrics.text_size)
+            .color(config.theme().palette().primary),
+        widget::text!("{remaining} s"),
+        dismiss
+    ]
+    .align_x(Alignment::Center)
+    .spacing(metrics.spacing);
 
-    fn initial_work_goal_prompt<'a>(work_goal: &str) -> Element<'a, Message> {
-        let text_input = widget::text_input("Work goal", work_goal)
-            .id("work-goal")
-            .on_input(Message::InitialWorkGoalChange)
-            .on_submit(Message::InitialWorkGoalSubmit);
-        let column = column!["Enter the goal of your fist work session", text_input]
-            .align_x(Alignment::Center)
-            .max_width(TIMER_TEXT_SIZE * 3.0);
-        Container::new(column)
-            .padding(SPACING)
-            .center(Length::Fill)
+    container(column).center(Length::Fill).into()
+}
+
+pub fn stats_view<'a>(
+    config: &Config,
+    metrics: Metrics,
+    stats: &StatsManager
+) -> Element<'a, Message> {
+    let palette = config.theme().palette();
+    let header = row![
+        container("").width(Length::Fill),
+        container("Pomodori").align_right(Length::Fill),
+        container("Sessions").align_right(Length::Fill),
+        container("Suggestions done").align_right(Length::Fill)
+    ];
+    let rows = stats
+        .counts()
+        .into_iter()
+        .zip(stats.suggestions_done())
+        .map(|((label, count), suggestions_done)| {
+            row![
+                container(label).width(Length::Fill),
+                container(widget::text(count.pomodori.to_string()).color(palette.primary))
+                    .align_right(Length::Fill),
+                container(widget::text(count.sessions.to_string()).color(palette.primary))
+                    .align_right(Length::Fill),
+                container(widget::text(suggestions_done.to_string()).color(palette.primary))
+                    .align_right(Length::Fill)
+            ]
             .into()
-    }
+        });
+    let column = column![
+        widget::text("Stats").size(metrics.text_size),
+        header,
+        rule::horizontal(2.0)
+    ]
+    .extend(rows)
+    .push(
+        button(widget::text(
+            config.keybindings.label("Back", Action::OpenStats)
+        ))
+        .on_press(Message::ToggleStats)
+    )
+    .spacing(metrics.spacing)
+    .width(metrics.timer_text_size * 4.0);
+    Container::new(column)
+        .padding(metrics.spacing)
+        .center(Length::Fill)
+        .into()
+}
+
+pub fn initial_work_goal_prompt<'a>(metrics: Metrics, work_goal: &str) -> Element<'a, Message> {
+    let text_input = widget::text_input("Work goal", work_goal)
+        .id("work-goal")
+        .on_input(Message::InitialWorkGoalChange)
+        .on_submit(Message::InitialWorkGoalSubmit);
+    let column = column!["Enter the goal of your fist work session", text_input]
+        .align_x(Alignment::Center)
+        .max_width(metrics.timer_text_size * 3.0);
+    Container::new(column)
+        .padding(metrics.spacing)
+        .center(Length::Fill)
+        .into()
 }
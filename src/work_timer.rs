@@ -1,5 +1,7 @@
 use std::time::{Duration, Instant};
 
+use crate::clock::Clock;
+
 #[derive(Debug, Clone, Copy)]
 pub struct WorkTimer {
     last_tick: Option<Instant>,
@@ -7,39 +9,144 @@ pub struct WorkTimer {
 }
 
 impl WorkTimer {
-    pub fn new(work_duration: Duration) -> Self {
+    pub fn new(work_duration: Duration, clock: &impl Clock) -> Self {
         Self {
-            last_tick: Some(Instant::now()),
+            last_tick: Some(clock.now()),
             work_duration_remaining: work_duration
         }
     }
 
+    /// A timer mirroring one that runs elsewhere, e.g. in `annoyodoro daemon`
+    pub fn mirror(remaining: Duration, paused: bool, clock: &impl Clock) -> Self {
+        Self {
+            last_tick: (!paused).then(|| clock.now()),
+            work_duration_remaining: remaining
+        }
+    }
+
     pub fn duration_remaning(&self) -> Duration {
         self.work_duration_remaining
     }
 
+    /// Until the whole seconds of the remaining duration change, plus a millisecond so that a
+    /// wakeup at that point lands past the change
+    pub fn until_next_second(&self) -> Duration {
+        Duration::from_nanos(self.work_duration_remaining.subsec_nanos().into())
+            + Duration::from_millis(1)
+    }
+
     pub fn is_paused(&self) -> bool {
         self.last_tick.is_none()
     }
 
-    pub fn toggle_pause(&mut self) {
+    pub fn toggle_pause(&mut self, clock: &impl Clock) {
         self.last_tick = match self.last_tick {
-            Some(tick) => {
-                self.work_duration_remaining =
-                    self.work_duration_remaining.saturating_sub(tick.elapsed());
+            Some(_) => {
+                self.on_tick(clock);
                 None
             }
-            None => Some(Instant::now())
+            None => Some(clock.now())
         }
     }
 
-    pub fn on_tick(&mut self) {
+    pub fn on_tick(&mut self, clock: &impl Clock) {
         if let Some(tick) = self.last_tick {
-            let now = Instant::now();
+            let now = clock.now();
             self.work_duration_remaining = self
                 .work_duration_remaining
-                .saturating_sub(now.duration_since(tick));
+                .saturating_sub(now.saturating_duration_since(tick));
             self.last_tick = Some(now);
         }
     }
+
+    pub fn snooze(&mut self, by: Duration) {
+        self.work_duration_remaining += by;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn counts_down_on_tick() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::new(25 * MINUTE, &clock);
+        clock.advance(MINUTE);
+        assert_eq!(timer.duration_remaning(), 25 * MINUTE);
+        timer.on_tick(&clock);
+        assert_eq!(timer.duration_remaning(), 24 * MINUTE);
+    }
+
+    #[test]
+    fn pause_resume_does_not_drift() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::new(25 * MINUTE, &clock);
+        for _ in 0..1000 {
+            clock.advance(Duration::from_millis(700));
+            timer.toggle_pause(&clock);
+            clock.advance(Duration::from_millis(300));
+            timer.on_tick(&clock);
+            timer.toggle_pause(&clock);
+        }
+
+        timer.on_tick(&clock);
+        assert_eq!(
+            timer.duration_remaning(),
+            25 * MINUTE - 1000 * Duration::from_millis(700)
+        );
+    }
+
+    #[test]
+    fn long_pause_keeps_the_remaining_duration() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::new(25 * MINUTE, &clock);
+        clock.advance(5 * MINUTE);
+        timer.toggle_pause(&clock);
+        assert!(timer.is_paused());
+
+        clock.advance(Duration::from_secs(60 * 60 * 24 * 3));
+        timer.on_tick(&clock);
+        assert_eq!(timer.duration_remaning(), 20 * MINUTE);
+
+        timer.toggle_pause(&clock);
+        assert!(!timer.is_paused());
+        clock.advance(MINUTE);
+        timer.on_tick(&clock);
+        assert_eq!(timer.duration_remaning(), 19 * MINUTE);
+    }
+
+    #[test]
+    fn stops_at_zero() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::new(MINUTE, &clock);
+        clock.advance(10 * MINUTE);
+        timer.on_tick(&clock);
+        assert!(timer.duration_remaning().is_zero());
+    }
+
+    #[test]
+    fn wakes_up_past_each_second() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::new(MINUTE - Duration::from_millis(300), &clock);
+        assert_eq!(timer.until_next_second(), Duration::from_millis(701));
+
+        for remaining_secs in (55..59).rev() {
+            clock.advance(timer.until_next_second());
+            timer.on_tick(&clock);
+            assert_eq!(timer.duration_remaning().as_secs(), remaining_secs);
+        }
+    }
+
+    #[test]
+    fn paused_mirror_does_not_count_down() {
+        let clock = FakeClock::new();
+        let mut timer = WorkTimer::mirror(MINUTE, true, &clock);
+        clock.advance(MINUTE);
+        timer.on_tick(&clock);
+        assert_eq!(timer.duration_remaning(), MINUTE);
+    }
 }